@@ -1,10 +1,19 @@
 use crate::db;
-use crate::db::desktop_entry::DesktopEntryEntity;
+use crate::db::alias::AliasEntity;
+use crate::db::desktop_entry::{pin_first, remove_stale, DesktopEntryEntity};
+use crate::db::pinned::PinnedEntity;
 use crate::db::plugin::PluginCommandEntity;
+use crate::db::query::QueryEntity;
+use crate::db::ranking::{rank_by_time, SystemClock};
 use crate::db::web::WebEntity;
 use crate::db::Database;
+use crate::freedesktop::desktop::{desktop_file_id, find_desktop_file};
+use crate::settings::AliasTarget;
+use crate::SETTINGS;
 use once_cell::sync::OnceCell;
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::Mutex;
 
@@ -16,31 +25,108 @@ pub struct Cache<'a> {
     de_history: OnceCell<Vec<DesktopEntryEntity<'a>>>,
     web_history: History<WebEntity<'a>>,
     plugin_history: History<PluginCommandEntity<'a>>,
+    query_history: OnceCell<Vec<QueryEntity<'a>>>,
+    pinned: OnceCell<Vec<String>>,
+    aliases: OnceCell<HashMap<String, AliasTarget>>,
 }
 
 impl Default for Cache<'_> {
     fn default() -> Self {
+        Self::new(db::DB.clone())
+    }
+}
+
+impl Cache<'_> {
+    pub fn new(db: Database) -> Self {
         Self {
-            db: Database::default(),
+            db,
             de_history: OnceCell::new(),
             web_history: Mutex::new(Default::default()),
             plugin_history: Mutex::new(Default::default()),
+            query_history: OnceCell::new(),
+            pinned: OnceCell::new(),
+            aliases: OnceCell::new(),
         }
     }
-}
 
-impl Cache<'_> {
+    // Desktop entry history without uninstalled applications, pinned entries first
     pub fn de_history(&self) -> &Vec<DesktopEntryEntity> {
         self.de_history.get_or_init(|| {
-            self.db
-                .get_all::<DesktopEntryEntity>(db::desktop_entry::COLLECTION)
+            let history = self
+                .db
+                .get_all::<DesktopEntryEntity>(db::desktop_entry::COLLECTION);
+            let history = remove_stale(history, &self.db, db::timestamp(), find_desktop_file);
+            let history = if SETTINGS.history.contextual_ranking {
+                rank_by_time(history, &SystemClock)
+            } else {
+                history
+            };
+            pin_first(
+                history,
+                self.pinned(),
+                DesktopEntryEntity::from_desktop_file,
+            )
         })
     }
 
+    // Pinned desktop file ids, from the config first then from the database
+    pub fn pinned(&self) -> &Vec<String> {
+        self.pinned.get_or_init(|| {
+            let mut pinned = SETTINGS.pinned.clone();
+            for id in PinnedEntity::all(&self.db) {
+                if !pinned.contains(&id) {
+                    pinned.push(id);
+                }
+            }
+            pinned
+        })
+    }
+
+    pub fn is_pinned(&self, path: &Path) -> bool {
+        desktop_file_id(path)
+            .map(|id| self.pinned().contains(&id))
+            .unwrap_or(false)
+    }
+
+    // Reload pinned entries and the desktop entry history on next access
+    pub fn reset_pinned(&mut self) {
+        self.pinned.take();
+        self.de_history.take();
+    }
+
     pub fn de_len(&self) -> usize {
         self.de_history.get().map(|de| de.len()).unwrap_or(0)
     }
 
+    // Submitted queries, most recent first
+    pub fn query_history(&self) -> &Vec<QueryEntity<'_>> {
+        self.query_history.get_or_init(|| {
+            let mut queries = self.db.get_all::<QueryEntity>(db::query::COLLECTION);
+            queries.sort_by_key(|query| Reverse(query.last_used));
+            queries
+        })
+    }
+
+    // Aliases added from the UI, overridden by the ones from the config
+    pub fn alias(&self, keyword: &str) -> Option<&AliasTarget> {
+        self.aliases
+            .get_or_init(|| {
+                let mut aliases: HashMap<_, _> = self
+                    .db
+                    .get_all::<AliasEntity>(db::alias::COLLECTION)
+                    .into_iter()
+                    .map(|alias| (alias.keyword.into_owned(), alias.target))
+                    .collect();
+                aliases.extend(SETTINGS.aliases.clone());
+                aliases
+            })
+            .get(keyword)
+    }
+
+    pub fn reset_aliases(&mut self) {
+        self.aliases.take();
+    }
+
     pub fn plugin_history(&self, plug_name: &str) -> Rc<Vec<PluginCommandEntity>> {
         let mut history = self.plugin_history.lock().unwrap();
         if history.get(plug_name).is_none() {
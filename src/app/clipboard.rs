@@ -0,0 +1,44 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::anyhow;
+use tracing::debug;
+
+use crate::SETTINGS;
+
+// Copy text to the system clipboard through an external helper,
+// the helper keeps serving the selection after onagre exits
+pub fn copy(text: &str) -> anyhow::Result<()> {
+    let args = match &SETTINGS.clipboard_command {
+        Some(command) => shell_words::split(command)?,
+        None if std::env::var_os("WAYLAND_DISPLAY").is_some() => vec!["wl-copy".to_string()],
+        None => vec![
+            "xclip".to_string(),
+            "-selection".to_string(),
+            "clipboard".to_string(),
+        ],
+    };
+
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("empty clipboard command"))?;
+
+    debug!("Copying {text:?} to clipboard with {program}");
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("failed to open {program} stdin"))?
+        .write_all(text.as_bytes())?;
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("{program} exited with {status}"));
+    }
+
+    Ok(())
+}
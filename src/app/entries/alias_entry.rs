@@ -0,0 +1,75 @@
+use iced::widget::Row;
+use std::borrow::Cow;
+
+use crate::app::entries::AsEntry;
+use crate::app::style::rows::RowStyles;
+use crate::app::Message;
+use crate::db::desktop_entry::DesktopEntryEntity;
+use crate::icons::IconPath;
+use crate::settings::AliasTarget;
+
+// The target of the alias matching the input, displayed above the other rows
+#[derive(Debug)]
+pub enum AliasEntry {
+    DesktopEntry(DesktopEntryEntity<'static>),
+    Query { keyword: String, query: String },
+}
+
+impl AliasEntry {
+    // Desktop entries which are not installed anymore are ignored
+    pub fn resolve(keyword: &str, target: &AliasTarget) -> Option<Self> {
+        match target {
+            AliasTarget::DesktopEntry(id) => {
+                DesktopEntryEntity::from_desktop_file(id).map(AliasEntry::DesktopEntry)
+            }
+            AliasTarget::Query(query) => Some(AliasEntry::Query {
+                keyword: keyword.to_string(),
+                query: query.clone(),
+            }),
+        }
+    }
+}
+
+impl<'a> AsEntry<'a> for AliasEntry {
+    fn get_icon_layout<'b>(
+        &'a self,
+        category_icon: Option<&'a IconPath>,
+        style: &'static RowStyles,
+    ) -> Row<'b, Message>
+    where
+        'b: 'a,
+    {
+        match self {
+            AliasEntry::DesktopEntry(entity) => entity.get_icon_layout(category_icon, style),
+            AliasEntry::Query { .. } => {
+                let icon = Self::build_icon(&style.icon, None::<&IconPath>);
+                Row::new().push(icon)
+            }
+        }
+    }
+
+    fn get_display_name(&self) -> &str {
+        match self {
+            AliasEntry::DesktopEntry(entity) => entity.name.as_ref(),
+            AliasEntry::Query { query, .. } => query,
+        }
+    }
+
+    fn get_description(&self) -> Option<Cow<'_, str>> {
+        match self {
+            AliasEntry::DesktopEntry(entity) => entity.get_description(),
+            AliasEntry::Query { keyword, .. } => Some(Cow::Owned(format!("Alias: {keyword}"))),
+        }
+    }
+
+    fn get_clipboard_text(&self) -> Option<Cow<'_, str>> {
+        match self {
+            AliasEntry::DesktopEntry(entity) => entity.get_clipboard_text(),
+            AliasEntry::Query { query, .. } => Some(Cow::Borrowed(query)),
+        }
+    }
+
+    fn on_press(&self, _idx: usize) -> Message {
+        Message::ClickAlias
+    }
+}
@@ -0,0 +1,44 @@
+use iced::widget::Row;
+use std::borrow::Cow;
+
+use crate::app::entries::AsEntry;
+use crate::app::style::rows::RowStyles;
+use crate::app::Message;
+use crate::icons::IconPath;
+use crate::THEME;
+
+// The result of the current calculator expression
+pub struct CalcEntry<'a> {
+    pub result: &'a str,
+    pub expression: &'a str,
+}
+
+impl<'a> AsEntry<'a> for CalcEntry<'a> {
+    // Like plugin history entries, the category icon is used as the main icon
+    fn get_icon_layout<'b>(
+        &'a self,
+        category_icon: Option<&'a IconPath>,
+        style: &'static RowStyles,
+    ) -> Row<'b, Message>
+    where
+        'b: 'a,
+    {
+        let icon = Self::build_icon(&style.icon, category_icon);
+        Row::new().push(icon)
+    }
+
+    fn get_display_name(&self) -> &str {
+        self.result
+    }
+
+    fn get_description(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.expression))
+    }
+}
+
+pub fn calculator_icon() -> Option<IconPath> {
+    THEME
+        .icon_theme
+        .as_ref()
+        .and_then(|theme| IconPath::lookup("accessories-calculator", theme, THEME.icon_size))
+}
@@ -1,6 +1,5 @@
 use iced::widget::Row;
 use std::borrow::Cow;
-use std::path::PathBuf;
 
 use crate::app::entries::AsEntry;
 use crate::app::mode::WEB_CONFIG;
@@ -9,8 +8,9 @@ use crate::app::Message;
 use crate::db::desktop_entry::DesktopEntryEntity;
 use crate::db::plugin::PluginCommandEntity;
 use crate::db::web::WebEntity;
+use crate::freedesktop::desktop::DesktopEntry;
 use crate::icons::{Extension, IconPath};
-use crate::THEME;
+use crate::{SETTINGS, THEME};
 
 impl<'a> AsEntry<'a> for DesktopEntryEntity<'_> {
     fn get_display_name(&self) -> &str {
@@ -27,8 +27,21 @@ impl<'a> AsEntry<'a> for DesktopEntryEntity<'_> {
         }
     }
 
+    // Tells apart the same application installed from several sources
     fn get_description(&self) -> Option<Cow<'_, str>> {
-        self.description.as_ref().cloned()
+        let origin = self.origin();
+        match &self.description {
+            Some(description) => Some(Cow::Owned(format!("{description} ({origin})"))),
+            None => Some(Cow::Owned(origin.to_string())),
+        }
+    }
+
+    // The Exec line without freedesktop field codes
+    fn get_clipboard_text(&self) -> Option<Cow<'_, str>> {
+        let desktop_entry = DesktopEntry::from_path(&self.path)?;
+        let args = shell_words::split(&desktop_entry.exec).ok()?;
+        let args = args.iter().filter(|arg| !arg.starts_with('%'));
+        Some(Cow::Owned(shell_words::join(args)))
     }
 }
 
@@ -61,59 +74,47 @@ impl<'a> AsEntry<'a> for WebEntity<'a> {
     }
 
     fn get_icon(&self) -> Option<IconPath> {
+        if let Some(shortcut) = SETTINGS.web_shortcut(&self.kind) {
+            return THEME.icon_theme.as_ref().and_then(|theme| {
+                shortcut
+                    .icon
+                    .as_ref()
+                    .and_then(|icon| IconPath::from_name_or_path(icon, theme))
+            });
+        }
+
         WEB_CONFIG
             .get(&self.kind)
             .and_then(|definition| definition.first().map(|def| &def.name))
-            .map(|web_query_kind| {
-                (
-                    dirs::cache_dir().unwrap().join("pop-launcher"),
-                    web_query_kind,
-                )
-            })
-            .and_then(|(path, filename)| {
-                // Unfortunately we need to copy .ico files to png extension for iced
-                // To render the icon
-                let path = path.join(format!("{}.png", &filename));
-                if path.exists() {
-                    Some(IconPath {
-                        path,
-                        extension: Extension::Png,
-                        symbolic: filename.ends_with("-symbolic"),
-                    })
-                } else if path.with_extension("ico").exists() {
-                    ico_to_png(path.with_extension("ico"));
-                    Some(IconPath {
-                        path,
-                        extension: Extension::Png,
-                        symbolic: filename.ends_with("-symbolic"),
-                    })
+            .and_then(|filename| {
+                let cache = dirs::cache_dir()?.join("pop-launcher");
+                // Favicons fetched by the pop-launcher web plugin,
+                // `.ico` files are decoded in memory, see `icons::raster`
+                let ico = cache.join(format!("{filename}.ico"));
+                let png = cache.join(format!("{filename}.png"));
+                let (path, extension) = if ico.exists() {
+                    (ico, Extension::Ico)
+                } else if png.exists() {
+                    (png, Extension::Png)
                 } else {
-                    None
-                }
+                    return None;
+                };
+
+                Some(IconPath {
+                    path,
+                    extension,
+                    symbolic: filename.ends_with("-symbolic"),
+                })
             })
     }
 
     fn get_description(&self) -> Option<Cow<'_, str>> {
         None
     }
-}
 
-// FIXME: This should be removed
-fn ico_to_png(path: PathBuf) {
-    let file = std::fs::File::open(&path).unwrap();
-    match ico::IconDir::read(file) {
-        Ok(icon) => {
-            for entry in icon.entries() {
-                if !entry.is_png() {
-                    let image = entry.decode().unwrap();
-                    let file = std::fs::File::create(&path.with_extension("png")).unwrap();
-                    image.write_png(file).unwrap();
-                }
-            }
-        }
-        Err(_) => {
-            // We were unable to read the icon, it's probably a png
-            std::fs::copy(&path, path.with_extension("png")).unwrap();
-        }
+    fn get_clipboard_text(&self) -> Option<Cow<'_, str>> {
+        self.url()
+            .map(Cow::Owned)
+            .or_else(|| Some(Cow::Borrowed(self.query.trim())))
     }
 }
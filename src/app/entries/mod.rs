@@ -8,8 +8,30 @@ use iced::widget::{column, container, row, text, Button, Container, Image, Row};
 use iced::{Alignment, Length, Renderer};
 use std::borrow::Cow;
 
+pub(crate) mod alias_entry;
+pub(crate) mod calc_entry;
 pub(crate) mod db_entry;
 pub(crate) mod pop_entry;
+pub(crate) mod script_entry;
+
+// The style of a row in a list supporting multi-selection and pinning,
+// selected rows take precedence over marked rows, then pinned rows
+pub(crate) fn row_style(
+    selected: Option<usize>,
+    marked: &[usize],
+    pinned: bool,
+    idx: usize,
+) -> &'static RowStyles {
+    if selected == Some(idx) {
+        &THEME.app_container.rows.row_selected
+    } else if marked.contains(&idx) {
+        &THEME.app_container.rows.row_marked
+    } else if pinned {
+        &THEME.app_container.rows.row_pinned
+    } else {
+        &THEME.app_container.rows.row
+    }
+}
 
 pub(crate) trait AsEntry<'a> {
     fn to_row<'b>(
@@ -21,8 +43,18 @@ pub(crate) trait AsEntry<'a> {
     where
         'b: 'a,
     {
-        let theme = self.get_style(selected, idx);
+        self.to_row_with_style(self.get_style(selected, idx), idx, category_icon)
+    }
 
+    fn to_row_with_style<'b>(
+        &'a self,
+        theme: &'static RowStyles,
+        idx: usize,
+        category_icon: Option<&'a IconPath>,
+    ) -> Container<'b, Message>
+    where
+        'b: 'a,
+    {
         let row = if THEME.icon_theme.is_some() {
             self.get_icon_layout(category_icon, theme)
         } else {
@@ -81,7 +113,7 @@ pub(crate) trait AsEntry<'a> {
 
         let button = Button::new(row.push(column))
             .style(iced::theme::Button::Custom(Box::new(&ButtonStyle)))
-            .on_press(Message::Click(idx));
+            .on_press(self.on_press(idx));
 
         Container::new(button)
             .style(iced::theme::Container::Custom(Box::new(theme)))
@@ -92,6 +124,10 @@ pub(crate) trait AsEntry<'a> {
             .align_y(theme.align_y)
     }
 
+    fn on_press(&self, idx: usize) -> Message {
+        Message::Click(idx)
+    }
+
     fn get_style(&self, selected: Option<usize>, idx: usize) -> &'static RowStyles {
         let selected = selected.map(|selected| selected == idx).unwrap_or(false);
         if selected {
@@ -128,25 +164,30 @@ pub(crate) trait AsEntry<'a> {
     where
         'b: 'a,
     {
-        let icon = match icon.as_ref() {
-            Some(icon) => match icon.as_ref().extension {
-                Extension::Svg => Container::new(
-                    icon.as_ref()
-                        .to_svg(&theme.color)
-                        .height(Length::Fixed(theme.icon_size as f32))
-                        .width(Length::Fixed(theme.icon_size as f32)),
-                ),
-                Extension::Png => Container::new(
-                    Image::new(&icon.as_ref().path)
-                        .height(Length::Fixed(theme.icon_size as f32))
-                        .width(Length::Fixed(theme.icon_size as f32)),
-                ),
-            },
-            None => Container::new(
+        let fallback = || {
+            Container::new(
                 fallback_icon(&theme.color)
                     .height(Length::Fixed(theme.icon_size as f32))
                     .width(Length::Fixed(theme.icon_size as f32)),
+            )
+        };
+
+        let icon = match icon.as_ref().map(|icon| icon.as_ref()) {
+            Some(icon) if icon.extension == Extension::Svg => Container::new(
+                icon.to_svg(&theme.color)
+                    .height(Length::Fixed(theme.icon_size as f32))
+                    .width(Length::Fixed(theme.icon_size as f32)),
             ),
+            // Raster icons which could not be decoded use the fallback icon
+            Some(icon) => match icon.to_image_handle() {
+                Some(handle) => Container::new(
+                    Image::new(handle)
+                        .height(Length::Fixed(theme.icon_size as f32))
+                        .width(Length::Fixed(theme.icon_size as f32)),
+                ),
+                None => fallback(),
+            },
+            None => fallback(),
         };
 
         icon
@@ -164,4 +205,9 @@ pub(crate) trait AsEntry<'a> {
         unreachable!()
     }
     fn get_description(&self) -> Option<Cow<'_, str>>;
+
+    // The text copied to the clipboard by the copy action
+    fn get_clipboard_text(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.get_display_name()))
+    }
 }
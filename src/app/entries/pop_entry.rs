@@ -12,13 +12,15 @@ impl<'a> AsEntry<'a> for PopSearchResult<'a> {
         self.0.name.as_str()
     }
 
+    // Icons are resolved asynchronously when results are received, see `icons::resolve_names`
     fn get_icon(&self) -> Option<IconPath> {
         match &THEME.icon_theme {
             Some(theme) => self
                 .0
                 .icon
                 .as_ref()
-                .and_then(|source| IconPath::from_source(source, theme)),
+                .and_then(|source| IconPath::from_source_cached(source, theme))
+                .flatten(),
             _ => None,
         }
     }
@@ -26,4 +28,17 @@ impl<'a> AsEntry<'a> for PopSearchResult<'a> {
     fn get_description(&self) -> Option<Cow<'_, str>> {
         Some(Cow::Borrowed(self.0.description.as_str()))
     }
+
+    // File and recent documents plugins put the path or uri in the description
+    fn get_clipboard_text(&self) -> Option<Cow<'_, str>> {
+        let description = self.0.description.as_str();
+        if description.starts_with('/')
+            || description.starts_with('~')
+            || description.contains("://")
+        {
+            Some(Cow::Borrowed(description))
+        } else {
+            Some(Cow::Borrowed(self.0.name.as_str()))
+        }
+    }
 }
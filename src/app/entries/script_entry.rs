@@ -0,0 +1,30 @@
+use std::borrow::Cow;
+
+use crate::app::entries::AsEntry;
+use crate::app::scripts::ScriptEntry;
+use crate::icons::IconPath;
+use crate::THEME;
+
+impl<'a> AsEntry<'a> for ScriptEntry {
+    fn get_display_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn get_icon(&self) -> Option<IconPath> {
+        match &THEME.icon_theme {
+            Some(theme) => self
+                .icon
+                .as_deref()
+                .and_then(|icon| IconPath::from_name_or_path(icon, theme)),
+            _ => None,
+        }
+    }
+
+    fn get_description(&self) -> Option<Cow<'_, str>> {
+        self.description.as_deref().map(Cow::Borrowed)
+    }
+
+    fn get_clipboard_text(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.value()))
+    }
+}
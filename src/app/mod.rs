@@ -1,9 +1,10 @@
 use std::path::Path;
 use std::process::exit;
+use std::time::Duration;
 
 use iced::alignment::{Horizontal, Vertical};
 use iced::futures::channel::mpsc::{Sender, TrySendError};
-use iced::keyboard::KeyCode;
+use iced::keyboard::{KeyCode, Modifiers};
 use iced::widget::{column, container, scrollable, text_input, Column, Container, Row, Text};
 use iced::window::PlatformSpecific;
 use iced::{
@@ -14,24 +15,33 @@ use iced_core::{Event, Font};
 use iced_style::Theme;
 use onagre_launcher_toolkit::launcher::{Request, Response};
 use once_cell::sync::Lazy;
-use tracing::{debug, trace};
+use tracing::{debug, error, trace, warn};
 
+use crate::app::entries::alias_entry::AliasEntry;
+use crate::app::entries::calc_entry::{calculator_icon, CalcEntry};
 use crate::app::entries::pop_entry::PopSearchResult;
-use crate::app::entries::AsEntry;
-use crate::app::mode::ActiveMode;
-use crate::app::state::{Selection, State};
+use crate::app::entries::{row_style, AsEntry};
+use crate::app::mode::{ActiveMode, CALCULATOR};
+use crate::app::scripts::ScriptEntry;
+use crate::app::state::{BatchStep, QueryRecall, Selection, State};
 use crate::app::subscriptions::pop_launcher::{PopLauncherSubscription, SubscriptionMessage};
+use crate::db::alias::AliasEntity;
 use crate::db::desktop_entry::DesktopEntryEntity;
+use crate::db::pinned::PinnedEntity;
 use crate::db::plugin::PluginCommandEntity;
+use crate::db::query::QueryEntity;
 use crate::db::web::WebEntity;
 use crate::freedesktop::desktop::DesktopEntry;
-use crate::icons::IconPath;
-use crate::THEME;
+use crate::icons::{self, IconPath};
+use crate::settings::AliasTarget;
+use crate::{calc, SETTINGS, THEME};
 
 pub mod cache;
+pub mod clipboard;
 pub mod entries;
 pub mod mode;
 pub mod plugin_matchers;
+pub mod scripts;
 pub mod state;
 pub mod style;
 pub mod subscriptions;
@@ -81,11 +91,18 @@ pub enum Message {
     Loading,
     InputChanged(String),
     Click(usize),
-    KeyboardEvent(KeyCode),
+    ClickAlias,
+    KeyboardEvent(Modifiers, KeyCode),
     SubscriptionResponse(SubscriptionMessage),
+    ScriptResults(String, Vec<ScriptEntry>),
+    IconsResolved,
     Unfocused,
+    ActivationTimeout(u64),
 }
 
+// How long a batch activation waits for pop-launcher before moving to the next row
+const ACTIVATION_TIMEOUT: Duration = Duration::from_secs(2);
+
 static INPUT_ID: Lazy<text_input::Id> = Lazy::new(text_input::Id::unique);
 static SCROLL_ID: Lazy<scrollable::Id> = Lazy::new(scrollable::Id::unique);
 
@@ -128,8 +145,14 @@ impl Application for Onagre<'_> {
         match message {
             Message::Loading => text_input::focus(INPUT_ID.clone()),
             Message::InputChanged(input) => self.on_input_changed(input),
-            Message::KeyboardEvent(event) => self.handle_input(event),
+            Message::KeyboardEvent(modifiers, key_code) => self.handle_input(modifiers, key_code),
             Message::SubscriptionResponse(message) => self.on_pop_launcher_message(message),
+            Message::ScriptResults(query, results) => {
+                self.on_script_results(query, results);
+                Command::none()
+            }
+            // Redraw with the resolved icons
+            Message::IconsResolved => Command::none(),
             Message::Unfocused => {
                 if THEME.exit_unfocused {
                     exit(0);
@@ -145,13 +168,32 @@ impl Application for Onagre<'_> {
 
                 self.on_execute()
             }
+            Message::ActivationTimeout(generation) => {
+                let step = self.state.batch.on_timeout(generation);
+                self.on_batch_step(step)
+            }
+            Message::ClickAlias => {
+                self.state.selected = Selection::Reset;
+                self.on_execute()
+            }
         }
     }
 
     fn view(&self) -> Element<'_, Self::Message, Renderer<Self::Theme>> {
         // Build rows from current mode search entries
         let selected = self.selected();
-        let rows = match &self.state.get_active_mode() {
+        let rows: Vec<Element<'_, Message>> = match &self.state.get_active_mode() {
+            _ if self.state.backend.show_diagnostics => self
+                .state
+                .backend
+                .diagnostics
+                .iter()
+                .map(|line| {
+                    Text::new(line)
+                        .size(THEME.app_container.rows.row.description.font_size)
+                        .into()
+                })
+                .collect(),
             ActiveMode::Plugin {
                 plugin_name,
                 history,
@@ -166,6 +208,56 @@ impl Application for Onagre<'_> {
                     .map(|(idx, entry)| entry.to_row(selected, idx, icon.as_ref()).into())
                     .collect()
             }
+            ActiveMode::Script {
+                plugin_name,
+                history,
+                ..
+            } => {
+                let icon = self.state.plugin_matchers.get_plugin_icon(plugin_name);
+                // Show previous queries until the script returns something
+                if *history && self.state.script_results.is_empty() {
+                    self.state
+                        .cache
+                        .plugin_history(plugin_name)
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, entry)| entry.to_row(selected, idx, icon.as_ref()).into())
+                        .collect()
+                } else {
+                    self.state
+                        .script_results
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, entry)| entry.to_row(selected, idx, icon.as_ref()).into())
+                        .collect()
+                }
+            }
+            ActiveMode::Calc { .. } => {
+                let icon = calculator_icon();
+                let expression = self.state.plugin_query(&SETTINGS.calculator.prefix);
+                let mut rows: Vec<Element<'_, Self::Message, Renderer<Self::Theme>>> = vec![];
+                // The result comes first, followed by previous expressions
+                if let Some(result) = &self.state.calc_result {
+                    let entry = CalcEntry {
+                        result,
+                        expression: expression.trim(),
+                    };
+                    rows.push(entry.to_row(selected, 0, icon.as_ref()).into());
+                }
+
+                let offset = rows.len();
+                rows.extend(
+                    self.state
+                        .cache
+                        .plugin_history(CALCULATOR)
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, entry)| {
+                            entry.to_row(selected, idx + offset, icon.as_ref()).into()
+                        }),
+                );
+                rows
+            }
             ActiveMode::Web { modifier, .. } => {
                 let icon = self.state.plugin_matchers.get_plugin_icon("web");
                 self.state
@@ -186,32 +278,66 @@ impl Application for Onagre<'_> {
                     .de_history()
                     .iter()
                     .enumerate()
-                    .map(|(idx, entry)| entry.to_row(selected, idx, icon.as_ref()).into())
+                    .map(|(idx, entry)| {
+                        let pinned = self.state.cache.is_pinned(&entry.path);
+                        let style = row_style(selected, &self.state.marked, pinned, idx);
+                        entry.to_row_with_style(style, idx, icon.as_ref()).into()
+                    })
                     .collect()
             }
             _ => self
                 .state
                 .pop_search
                 .iter()
-                .map(|entry| {
+                .enumerate()
+                .map(|(idx, entry)| {
                     let icon = match &THEME.icon_theme {
                         Some(theme) => entry
                             .category_icon
                             .as_ref()
-                            .and_then(|source| IconPath::from_source(source, theme)),
+                            .and_then(|source| IconPath::from_source_cached(source, theme))
+                            .flatten(),
                         _ => None,
                     };
 
+                    let style = row_style(selected, &self.state.marked, false, idx);
                     PopSearchResult(entry)
-                        .to_row(selected, entry.id as usize, icon.as_ref())
+                        .to_row_with_style(style, idx, icon.as_ref())
                         .into()
                 })
                 .collect(),
         };
 
+        // An exact alias match is displayed first, and selected until moving down
+        let rows = match &self.state.alias {
+            Some(alias) if !self.state.backend.show_diagnostics => {
+                let style = if selected.is_none() {
+                    &THEME.app_container.rows.row_selected
+                } else {
+                    &THEME.app_container.rows.row
+                };
+                let icon = self
+                    .state
+                    .plugin_matchers
+                    .get_plugin_icon("desktop_entries");
+                let alias_row = alias.to_row_with_style(style, 0, icon.as_ref()).into();
+                std::iter::once(alias_row).chain(rows).collect()
+            }
+            _ => rows,
+        };
+
+        // Let the user know the backend crashed and was restarted
+        let rows = match &self.state.backend.notice {
+            Some(notice) => column(vec![Text::new(notice)
+                .size(THEME.app_container.rows.row.description.font_size)
+                .into()])
+            .push(column(rows)),
+            None => column(rows),
+        };
+
         // Scrollable element containing the rows
         let scrollable =
-            scrollable(column(rows))
+            scrollable(rows)
                 .id(SCROLL_ID.clone())
                 .style(iced::theme::Scrollable::Custom(Box::new(
                     THEME.scrollable(),
@@ -225,7 +351,12 @@ impl Application for Onagre<'_> {
             .width(THEME.app_container.rows.width)
             .height(THEME.app_container.rows.height); // TODO: add this to stylesheet
 
-        let text_input = text_input("Search", &self.state.input_value.input_display)
+        let placeholder = match self.state.pending_alias {
+            Some(_) => "Alias keyword",
+            None => "Search",
+        };
+
+        let text_input = text_input(placeholder, &self.state.input_value.input_display)
             .on_input(Message::InputChanged)
             .id(INPUT_ID.clone())
             .style(iced::theme::TextInput::Custom(Box::new(
@@ -358,7 +489,18 @@ impl Onagre<'_> {
     }
 
     fn on_input_changed(&mut self, input: String) -> Command<Message> {
+        self.state.backend.notice = None;
+        self.state.marked.clear();
+        self.state.recall = None;
         self.state.set_input(&input);
+        if !matches!(self.state.get_active_mode(), ActiveMode::Script { .. }) {
+            self.state.script_results.clear();
+        }
+
+        if !matches!(self.state.get_active_mode(), ActiveMode::Calc { .. }) {
+            self.state.calc_result = None;
+        }
+
         self.state.selected = match self.state.get_active_mode() {
             // For those mode first line is unselected on change
             // We want to issue a pop-launcher search request to get the query at index 0 in
@@ -368,10 +510,48 @@ impl Onagre<'_> {
             _ => Selection::PopLauncher(0),
         };
 
+        let keyword = input.trim();
+        self.state.alias = match self.state.pending_alias {
+            Some(_) => None,
+            None => self
+                .state
+                .cache
+                .alias(keyword)
+                .and_then(|target| AliasEntry::resolve(keyword, target)),
+        };
+
+        if self.state.alias.is_some() {
+            self.state.selected = Selection::Reset;
+        }
+
         let _: Command<Message> = scrollable::snap_to(SCROLL_ID.clone(), RelativeOffset::START);
 
         match &self.state.get_active_mode() {
             ActiveMode::History => {}
+            ActiveMode::Calc { .. } => {
+                let expression = self.state.plugin_query(&SETTINGS.calculator.prefix);
+                self.state.calc_result = match calc::evaluate(&expression) {
+                    Ok(result) => Some(result),
+                    Err(err) => {
+                        trace!("Unable to evaluate {expression:?}: {err}");
+                        None
+                    }
+                };
+            }
+            ActiveMode::Script { plugin_name, .. } => {
+                if let Some(plugin) = SETTINGS.script_plugin(plugin_name) {
+                    let query = self.state.plugin_query(&plugin.prefix);
+                    return Command::batch([
+                        text_input::focus(INPUT_ID.clone()),
+                        Command::perform(
+                            scripts::search(plugin.clone(), query),
+                            |(query, results)| Message::ScriptResults(query, results),
+                        ),
+                    ]);
+                }
+            }
+            // Onagre web shortcuts only display the history
+            ActiveMode::Web { modifier } if SETTINGS.web_shortcut(modifier).is_some() => {}
             _ => {
                 let value = self.state.get_input();
 
@@ -384,7 +564,19 @@ impl Onagre<'_> {
     }
 
     fn run_command<P: AsRef<Path>>(&self, desktop_entry_path: P) -> Command<Message> {
-        let desktop_entry = DesktopEntry::from_path(&desktop_entry_path).unwrap();
+        self.launch(desktop_entry_path);
+        exit(0);
+    }
+
+    fn launch<P: AsRef<Path>>(&self, desktop_entry_path: P) {
+        // The application might have been uninstalled while onagre was open
+        let Some(desktop_entry) = DesktopEntry::from_path(&desktop_entry_path) else {
+            error!(
+                "Unable to read desktop entry {:?}",
+                desktop_entry_path.as_ref()
+            );
+            return;
+        };
 
         DesktopEntryEntity::persist(
             &desktop_entry,
@@ -400,31 +592,74 @@ impl Onagre<'_> {
             .filter(|entry| !entry.starts_with('%'))
             .collect::<Vec<&String>>();
 
-        std::process::Command::new(args[0])
+        let mut child = std::process::Command::new(args[0])
             .args(&args[1..])
             .spawn()
             .expect("Command failure");
 
-        exit(0);
+        // Onagre stays open until every marked row is launched, don't leave zombies behind
+        std::thread::spawn(move || child.wait());
     }
 
-    fn handle_input(&mut self, key_code: KeyCode) -> Command<Message> {
+    fn handle_input(&mut self, modifiers: Modifiers, key_code: KeyCode) -> Command<Message> {
+        if SETTINGS.keybindings.copy.matches(modifiers, key_code) {
+            return self.on_copy();
+        }
+
+        if SETTINGS.keybindings.recall.matches(modifiers, key_code) {
+            return self.recall_previous();
+        }
+
+        if SETTINGS.keybindings.mark.matches(modifiers, key_code) {
+            self.toggle_mark();
+            return Command::none();
+        }
+
+        if SETTINGS.keybindings.alias.matches(modifiers, key_code) {
+            return self.start_alias();
+        }
+
+        if SETTINGS.keybindings.pin.matches(modifiers, key_code) {
+            self.toggle_pin();
+            return Command::none();
+        }
+
+        if modifiers == Modifiers::SHIFT && matches!(key_code, KeyCode::Up | KeyCode::Down) {
+            return self.extend_marked(key_code);
+        }
+
         match key_code {
             KeyCode::Up => {
                 trace!("Selected line : {:?}", self.selected());
+                let at_top = matches!(self.selected(), None | Some(0));
+                if at_top && (self.state.recall.is_some() || self.state.get_input().is_empty()) {
+                    return self.recall_previous();
+                }
+
                 return self.dec_selected();
             }
             KeyCode::Down => {
                 trace!("Selected line : {:?}", self.selected());
+                self.state.recall = None;
                 return self.inc_selected();
             }
             KeyCode::Enter => return self.on_execute(),
             KeyCode::Tab => {
+                if matches!(
+                    self.state.get_active_mode(),
+                    ActiveMode::Script { .. } | ActiveMode::Calc { .. }
+                ) {
+                    return Command::none();
+                }
+
                 if let Some(selected) = self.selected() {
-                    self.pop_request(Request::Complete(selected as u32))
+                    self.pop_request(Request::Complete(self.pop_id(selected)))
                         .expect("Unable to send request to pop-launcher");
                 }
             }
+            KeyCode::F12 => {
+                self.state.backend.show_diagnostics = !self.state.backend.show_diagnostics;
+            }
             KeyCode::Escape => {
                 exit(0);
             }
@@ -450,12 +685,22 @@ impl Onagre<'_> {
             SubscriptionMessage::Ready(sender) => {
                 self.request_tx = Some(sender);
             }
+            SubscriptionMessage::BackendRestarted => {
+                self.state.exec_on_next_search = false;
+                self.state.backend.notice = Some("pop-launcher backend restarted".to_string());
+            }
+            SubscriptionMessage::Stderr(line) => self.state.backend.push_diagnostic(line),
             SubscriptionMessage::PopMessage(response) => match response {
-                Response::Close => exit(0),
+                Response::Close => {
+                    let step = self.state.batch.on_launched();
+                    return self.on_batch_step(step);
+                }
                 Response::Context { .. } => todo!("Discrete graphics is not implemented"),
                 Response::DesktopEntry { path, .. } => {
-                    debug!("Launch DesktopEntry {path:?} via run_command");
-                    let _ = self.run_command(path);
+                    debug!("Launch DesktopEntry {path:?}");
+                    self.launch(path);
+                    let step = self.state.batch.on_launched();
+                    return self.on_batch_step(step);
                 }
                 Response::Update(search_updates) => {
                     if self.state.exec_on_next_search {
@@ -464,15 +709,37 @@ impl Onagre<'_> {
                             .expect("Unable to send Activate request to pop-launcher");
                         return Command::none();
                     }
+                    self.state.batch.abort();
                     self.state.pop_search = search_updates;
+                    if SETTINGS.boost_pinned {
+                        self.boost_pinned();
+                    }
+
+                    return self.resolve_icons();
+                }
+                Response::Fill(fill) => {
+                    self.state.batch.abort();
+                    self.complete(fill);
                 }
-                Response::Fill(fill) => self.complete(fill),
             },
         };
 
         Command::none()
     }
 
+    fn on_script_results(&mut self, query: String, results: Vec<ScriptEntry>) {
+        if let ActiveMode::Script { plugin_name, .. } = self.state.get_active_mode() {
+            let current_query = SETTINGS
+                .script_plugin(plugin_name)
+                .map(|plugin| self.state.plugin_query(&plugin.prefix));
+
+            // Ignore results for outdated queries
+            if current_query.as_deref() == Some(query.as_str()) {
+                self.state.script_results = results;
+            }
+        }
+    }
+
     fn complete(&mut self, fill: String) {
         let filled = if THEME.plugin_hint().is_none() {
             self.state.input_value.input_display = fill;
@@ -492,6 +759,29 @@ impl Onagre<'_> {
     }
 
     fn on_execute(&mut self) -> Command<Message> {
+        if let Some(target) = self.state.pending_alias.take() {
+            return self.add_alias(target);
+        }
+
+        let query = self.state.get_input();
+        if !query.trim().is_empty() {
+            if self.state.recall.is_some() {
+                QueryEntity::touch(&query, &self.state.cache.db);
+            } else {
+                QueryEntity::persist(&query, &self.state.cache.db);
+            }
+        }
+
+        if self.selected().is_none() {
+            if let Some(alias) = self.state.alias.take() {
+                return self.activate_alias(alias);
+            }
+        }
+
+        if !self.state.marked.is_empty() && self.markable() {
+            return self.activate_marked();
+        }
+
         match &self.state.get_active_mode() {
             ActiveMode::Plugin {
                 plugin_name,
@@ -517,6 +807,38 @@ impl Onagre<'_> {
                         .expect("Unable to send pop-launcher request");
                 }
             }
+            ActiveMode::Web { modifier, .. } if SETTINGS.web_shortcut(modifier).is_some() => {
+                let query = match self.selected() {
+                    None => {
+                        let input = self.state.get_input();
+                        input
+                            .strip_prefix(modifier.as_str())
+                            .unwrap_or(&input)
+                            .to_string()
+                    }
+                    Some(selected) => self
+                        .state
+                        .cache
+                        .web_history(modifier)
+                        .get(selected)
+                        .map(|entry| entry.query.to_string())
+                        .unwrap_or_default(),
+                };
+
+                WebEntity::persist(&query, modifier, &self.state.cache.db);
+                let entity = WebEntity {
+                    query: query.into(),
+                    kind: modifier.into(),
+                    weight: 0,
+                    last_used: 0,
+                };
+
+                if let Some(url) = entity.url() {
+                    open_url(&url);
+                }
+
+                exit(0);
+            }
             ActiveMode::Web { modifier, .. } => {
                 let query = self.state.get_input();
                 let query = query.strip_prefix(modifier).unwrap();
@@ -539,22 +861,461 @@ impl Onagre<'_> {
                 let path = path.unwrap();
                 let _ = self.run_command(path);
             }
-            _ => {
-                if self.selected().is_none() {
-                    self.pop_request(Request::Activate(0))
-                        .expect("Unable to send pop-launcher request")
-                } else {
-                    let selected = self.selected().unwrap() as u32;
-                    debug!("Activating pop entry at index {selected}");
-                    self.pop_request(Request::Activate(selected))
-                        .expect("Unable to send pop-launcher request")
+            ActiveMode::Script {
+                plugin_name,
+                history,
+                ..
+            } => {
+                let plugin_name = plugin_name.clone();
+                let history = *history;
+                let Some(plugin) = SETTINGS.script_plugin(&plugin_name) else {
+                    return Command::none();
+                };
+
+                let selected = self.selected().unwrap_or(0);
+                if self.state.script_results.is_empty() {
+                    // Restore a previous query from the history
+                    let query = self
+                        .state
+                        .cache
+                        .plugin_history(&plugin_name)
+                        .get(selected)
+                        .map(|entry| entry.query.to_string());
+
+                    if let Some(query) = query.filter(|_| history) {
+                        let input = if THEME.plugin_hint().is_none() {
+                            format!("{}{query}", plugin.prefix)
+                        } else {
+                            query
+                        };
+
+                        return self.on_input_changed(input);
+                    }
+                } else if let Some(entry) = self.state.script_results.get(selected) {
+                    if history {
+                        PluginCommandEntity::persist(
+                            &plugin_name,
+                            &self.state.plugin_query(&plugin.prefix),
+                            &self.state.cache.db,
+                        );
+                    }
+
+                    if let Err(err) = scripts::activate(plugin, entry) {
+                        error!("Failed to activate script plugin '{plugin_name}': {err}");
+                    }
+
+                    exit(0);
+                }
+            }
+            ActiveMode::Calc { .. } => {
+                let selected = self.selected().unwrap_or(0);
+                let prefix = &SETTINGS.calculator.prefix;
+                match &self.state.calc_result {
+                    Some(result) if selected == 0 => {
+                        PluginCommandEntity::persist(
+                            CALCULATOR,
+                            self.state.plugin_query(prefix).trim(),
+                            &self.state.cache.db,
+                        );
+
+                        if let Err(err) = clipboard::copy(result) {
+                            error!("Failed to copy calculator result: {err}");
+                        }
+
+                        exit(0);
+                    }
+                    result => {
+                        // Restore a previous expression from the history
+                        let idx = selected - usize::from(result.is_some());
+                        let expression = self
+                            .state
+                            .cache
+                            .plugin_history(CALCULATOR)
+                            .get(idx)
+                            .map(|entry| entry.query.to_string());
+
+                        if let Some(expression) = expression {
+                            let input = if THEME.plugin_hint().is_none() {
+                                format!("{prefix}{expression}")
+                            } else {
+                                expression
+                            };
+
+                            return self.on_input_changed(input);
+                        }
+                    }
                 }
             }
+            _ => {
+                let selected = self.pop_id(self.selected().unwrap_or(0));
+                debug!("Activating pop entry {selected}");
+                self.pop_request(Request::Activate(selected))
+                    .expect("Unable to send pop-launcher request")
+            }
         }
 
         Command::none()
     }
 
+    // Replace the input with the previous submitted query containing the recall term,
+    // the plugin mode is detected again from the restored query
+    fn recall_previous(&mut self) -> Command<Message> {
+        let (term, position) = match &self.state.recall {
+            Some(recall) => (recall.term.clone(), recall.position + 1),
+            None => (self.state.get_input(), 0),
+        };
+
+        let query = self
+            .state
+            .cache
+            .query_history()
+            .iter()
+            .map(|entity| entity.query.as_ref())
+            .filter(|query| *query != term && query.contains(term.as_str()))
+            .nth(position)
+            .map(str::to_string);
+
+        let Some(query) = query else {
+            debug!("No previous query matching {term:?}");
+            return Command::none();
+        };
+
+        self.state.input_value.modifier_display.clear();
+        let command = self.on_input_changed(query);
+        self.state.recall = Some(QueryRecall { term, position });
+        Command::batch([command, text_input::move_cursor_to_end(INPUT_ID.clone())])
+    }
+
+    // Pin or unpin the selected history entry, entries pinned in the config can't be unpinned
+    fn toggle_pin(&mut self) {
+        if !matches!(self.state.get_active_mode(), ActiveMode::History) {
+            return;
+        }
+
+        let id = self
+            .selected()
+            .and_then(|selected| self.state.cache.de_history().get(selected))
+            .and_then(|entry| entry.desktop_file_id());
+
+        let Some(id) = id else {
+            return;
+        };
+
+        if SETTINGS.pinned.contains(&id) {
+            warn!("{id} is pinned in config.toml, remove it from `pinned` to unpin it");
+            return;
+        }
+
+        PinnedEntity::toggle(&id, &self.state.cache.db);
+        self.state.marked.clear();
+        self.state.cache.reset_pinned();
+    }
+
+    // Launch the alias target, queries are run as if they were typed
+    fn activate_alias(&mut self, alias: AliasEntry) -> Command<Message> {
+        match alias {
+            AliasEntry::DesktopEntry(entity) => {
+                self.launch(&entity.path);
+                exit(0);
+            }
+            AliasEntry::Query { query, .. } => {
+                self.state.pop_search.clear();
+                let command = self.on_input_changed(query);
+                // Don't follow an alias pointing to another keyword
+                self.state.alias = None;
+
+                match self.state.get_active_mode() {
+                    // Script results are not available yet
+                    ActiveMode::Script { .. } => command,
+                    // Evaluated by onagre, nothing to wait for
+                    ActiveMode::Calc { .. } | ActiveMode::History => {
+                        Command::batch([command, self.on_execute()])
+                    }
+                    ActiveMode::Web { modifier } if SETTINGS.web_shortcut(modifier).is_some() => {
+                        Command::batch([command, self.on_execute()])
+                    }
+                    // Activate once pop-launcher answered the search
+                    _ => {
+                        self.state.exec_on_next_search = true;
+                        command
+                    }
+                }
+            }
+        }
+    }
+
+    // Ask for a keyword activating the selected entry, the alias is saved on `Enter`
+    fn start_alias(&mut self) -> Command<Message> {
+        let Some(target) = self.alias_target() else {
+            debug!("Nothing to alias");
+            return Command::none();
+        };
+
+        let command = self.on_input_changed(String::new());
+        self.state.pending_alias = Some(target);
+        command
+    }
+
+    fn alias_target(&self) -> Option<AliasTarget> {
+        let selected = self.selected();
+        match self.state.get_active_mode() {
+            ActiveMode::History => self
+                .state
+                .cache
+                .de_history()
+                .get(selected?)?
+                .desktop_file_id()
+                .map(AliasTarget::DesktopEntry),
+            ActiveMode::DesktopEntry => self
+                .state
+                .pop_search
+                .get(selected.unwrap_or(0))
+                .map(|entry| AliasTarget::Query(entry.name.clone())),
+            _ => {
+                let query = selected
+                    .and_then(|_| self.current_entry())
+                    .unwrap_or_else(|| self.state.get_input());
+                Some(query)
+                    .filter(|query| !query.trim().is_empty())
+                    .map(AliasTarget::Query)
+            }
+        }
+    }
+
+    // Save the typed keyword and display the new alias
+    fn add_alias(&mut self, target: AliasTarget) -> Command<Message> {
+        let keyword = self.state.get_input().trim().to_string();
+        if keyword.is_empty() {
+            return Command::none();
+        }
+
+        AliasEntity::persist(&keyword, target, &self.state.cache.db);
+        self.state.cache.reset_aliases();
+        let command = self.on_input_changed(keyword);
+        Command::batch([command, text_input::move_cursor_to_end(INPUT_ID.clone())])
+    }
+
+    // Move pinned desktop entries first in search results, keeping pop-launcher order otherwise
+    fn boost_pinned(&mut self) {
+        if !matches!(self.state.get_active_mode(), ActiveMode::DesktopEntry) {
+            return;
+        }
+
+        let cache = &self.state.cache;
+        let pinned: Vec<&str> = cache
+            .de_history()
+            .iter()
+            .filter(|entry| cache.is_pinned(&entry.path))
+            .map(|entry| entry.name.as_ref())
+            .collect();
+
+        self.state
+            .pop_search
+            .sort_by_key(|entry| !pinned.contains(&entry.name.as_str()));
+    }
+
+    // Look up the icons of search results which were never resolved in the background
+    fn resolve_icons(&self) -> Command<Message> {
+        let Some(theme) = &THEME.icon_theme else {
+            return Command::none();
+        };
+
+        let mut names = vec![];
+        for entry in &self.state.pop_search {
+            let sources = entry.icon.iter().chain(entry.category_icon.iter());
+            for source in sources {
+                let resolved = IconPath::from_source_cached(source, theme).is_some();
+                let name = icons::source_name(source).into_owned();
+                if !resolved && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        if names.is_empty() {
+            return Command::none();
+        }
+
+        Command::perform(icons::resolve_names(names, theme.clone()), |()| {
+            Message::IconsResolved
+        })
+    }
+
+    // Search results can be reordered, pop-launcher expects the id it sent
+    fn pop_id(&self, position: usize) -> u32 {
+        self.state
+            .pop_search
+            .get(position)
+            .map(|entry| entry.id)
+            .unwrap_or(position as u32)
+    }
+
+    // Rows can only be marked when they are launchable entries
+    fn markable(&self) -> bool {
+        match self.state.get_active_mode() {
+            ActiveMode::History | ActiveMode::DesktopEntry => true,
+            ActiveMode::Plugin { history, .. } => !history,
+            _ => false,
+        }
+    }
+
+    fn toggle_mark(&mut self) {
+        let Some(selected) = self.selected().filter(|_| self.markable()) else {
+            return;
+        };
+
+        match self.state.marked.iter().position(|idx| *idx == selected) {
+            Some(position) => {
+                self.state.marked.remove(position);
+            }
+            None => self.state.marked.push(selected),
+        }
+    }
+
+    // Mark both the current and the next row while moving the selection
+    fn extend_marked(&mut self, key_code: KeyCode) -> Command<Message> {
+        if !self.markable() {
+            return Command::none();
+        }
+
+        self.mark_selected();
+        let command = if key_code == KeyCode::Up {
+            self.dec_selected()
+        } else {
+            self.inc_selected()
+        };
+        self.mark_selected();
+        command
+    }
+
+    fn mark_selected(&mut self) {
+        if let Some(selected) = self.selected() {
+            if !self.state.marked.contains(&selected) {
+                self.state.marked.push(selected);
+            }
+        }
+    }
+
+    // Launch every marked row in marking order
+    fn activate_marked(&mut self) -> Command<Message> {
+        let marked = std::mem::take(&mut self.state.marked);
+        if let ActiveMode::History = self.state.get_active_mode() {
+            let paths: Vec<_> = marked
+                .iter()
+                .filter_map(|idx| self.state.cache.de_history().get(*idx))
+                .map(|entry| entry.path.clone())
+                .collect();
+
+            for path in paths {
+                self.launch(path);
+            }
+
+            exit(0);
+        }
+
+        let ids: Vec<_> = marked.iter().map(|idx| self.pop_id(*idx)).collect();
+        let step = self.state.batch.start(ids);
+        self.on_batch_step(step)
+    }
+
+    fn on_batch_step(&mut self, step: BatchStep) -> Command<Message> {
+        match step {
+            BatchStep::Activate { id, generation } => {
+                debug!("Activating marked pop entry {id}");
+                self.pop_request(Request::Activate(id))
+                    .expect("Unable to send pop-launcher request");
+
+                // Plugins are not required to answer an activation
+                Command::perform(
+                    async { tokio::time::sleep(ACTIVATION_TIMEOUT).await },
+                    move |()| Message::ActivationTimeout(generation),
+                )
+            }
+            BatchStep::Exit => exit(0),
+            BatchStep::Stay => Command::none(),
+        }
+    }
+
+    // Copy the selected row instead of launching it
+    fn on_copy(&self) -> Command<Message> {
+        let Some(text) = self.selected_clipboard_text() else {
+            debug!("Nothing to copy");
+            return Command::none();
+        };
+
+        if let Err(err) = clipboard::copy(&text) {
+            error!("Failed to copy {text:?} to clipboard: {err}");
+            return Command::none();
+        }
+
+        exit(0);
+    }
+
+    fn selected_clipboard_text(&self) -> Option<String> {
+        let selected = self.selected();
+        if let (None, Some(alias)) = (selected, &self.state.alias) {
+            return clipboard_text(alias);
+        }
+
+        match &self.state.get_active_mode() {
+            ActiveMode::History => clipboard_text(self.state.cache.de_history().get(selected?)?),
+            ActiveMode::Plugin {
+                plugin_name,
+                history,
+                ..
+            } if *history => match selected {
+                None => clipboard_text(&PopSearchResult(self.state.pop_search.first()?)),
+                Some(selected) => {
+                    clipboard_text(self.state.cache.plugin_history(plugin_name).get(selected)?)
+                }
+            },
+            ActiveMode::Web { modifier, .. } => match selected {
+                // The web query currently typed
+                None => {
+                    let input = self.state.get_input();
+                    let query = input.strip_prefix(modifier.as_str()).unwrap_or(&input);
+                    let entity = WebEntity {
+                        query: query.into(),
+                        kind: modifier.into(),
+                        weight: 0,
+                        last_used: 0,
+                    };
+                    clipboard_text(&entity)
+                }
+                Some(selected) => {
+                    clipboard_text(self.state.cache.web_history(modifier).get(selected)?)
+                }
+            },
+            ActiveMode::Script {
+                plugin_name,
+                history,
+                ..
+            } => {
+                let selected = selected.unwrap_or(0);
+                if *history && self.state.script_results.is_empty() {
+                    clipboard_text(self.state.cache.plugin_history(plugin_name).get(selected)?)
+                } else {
+                    clipboard_text(self.state.script_results.get(selected)?)
+                }
+            }
+            ActiveMode::Calc { .. } => {
+                let selected = selected.unwrap_or(0);
+                match &self.state.calc_result {
+                    Some(result) if selected == 0 => Some(result.clone()),
+                    result => {
+                        let idx = selected - usize::from(result.is_some());
+                        clipboard_text(self.state.cache.plugin_history(CALCULATOR).get(idx)?)
+                    }
+                }
+            }
+            _ => {
+                let selected = selected.unwrap_or(0);
+                let entry = self.state.pop_search.get(selected)?;
+                clipboard_text(&PopSearchResult(entry))
+            }
+        }
+    }
+
     fn current_entries_len(&self) -> usize {
         match &self.state.get_active_mode() {
             ActiveMode::Plugin {
@@ -568,6 +1329,21 @@ impl Onagre<'_> {
                     self.state.pop_search.len()
                 }
             }
+            ActiveMode::Script {
+                plugin_name,
+                history,
+                ..
+            } => {
+                if *history && self.state.script_results.is_empty() {
+                    self.state.cache.plugin_history_len(plugin_name)
+                } else {
+                    self.state.script_results.len()
+                }
+            }
+            ActiveMode::Calc { .. } => {
+                usize::from(self.state.calc_result.is_some())
+                    + self.state.cache.plugin_history_len(CALCULATOR)
+            }
             ActiveMode::History => self.state.cache.de_len(),
             ActiveMode::DesktopEntry => self.state.pop_search.len(),
             ActiveMode::Web { modifier, .. } => self.state.cache.web_history_len(modifier),
@@ -591,6 +1367,10 @@ impl Onagre<'_> {
     fn dec_selected(&mut self) -> Command<Message> {
         match self.state.selected {
             Selection::Reset => self.state.selected = Selection::Reset,
+            // Moving up from the first row selects the alias row
+            Selection::History(0) | Selection::PopLauncher(0) if self.state.alias.is_some() => {
+                self.state.selected = Selection::Reset
+            }
             Selection::History(selected) => {
                 if selected > 0 {
                     self.state.selected = Selection::History(selected - 1)
@@ -630,10 +1410,219 @@ impl Onagre<'_> {
         subscription::events_with(|event, _status| match event {
             Event::Window(window::Event::Unfocused) => Some(Message::Unfocused),
             Event::Keyboard(iced::keyboard::Event::KeyPressed {
-                modifiers: _,
+                modifiers,
                 key_code,
-            }) => Some(Message::KeyboardEvent(key_code)),
+            }) => Some(Message::KeyboardEvent(modifiers, key_code)),
             _ => None,
         })
     }
 }
+
+fn clipboard_text<'a, E: AsEntry<'a>>(entry: &E) -> Option<String> {
+    entry.get_clipboard_text().map(|text| text.into_owned())
+}
+
+// Open a url with the default browser, detached from onagre
+fn open_url(url: &str) {
+    debug!("Opening {url}");
+    if let Err(err) = std::process::Command::new("xdg-open").arg(url).spawn() {
+        error!("Failed to open {url}: {err}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::app::cache::Cache;
+    use crate::app::mode::ActiveMode;
+    use crate::app::state::{
+        BackendStatus, BatchActivation, BatchStep, PluginConfigCache, SearchInput, Selection, State,
+    };
+    use crate::app::subscriptions::pop_launcher::SubscriptionMessage;
+    use crate::app::{Message, Onagre};
+    use crate::db::query::{self, QueryEntity};
+    use crate::db::Database;
+    use iced::futures::channel::mpsc::{channel, Receiver};
+    use iced::keyboard::KeyCode;
+    use iced::Application;
+    use onagre_launcher_toolkit::launcher::{Request, Response, SearchResult};
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    // Desktop entry search results with pop-launcher ids 10, 11, 12...
+    fn onagre<'a>(rows: u32) -> (Onagre<'a>, Receiver<Request>) {
+        let (request_tx, request_rx) = channel(8);
+        let pop_search = (0..rows)
+            .map(|idx| SearchResult {
+                id: 10 + idx,
+                name: format!("App {idx}"),
+                description: String::new(),
+                icon: None,
+                category_icon: None,
+                window: None,
+            })
+            .collect();
+
+        let state = State {
+            input_value: SearchInput {
+                mode: ActiveMode::DesktopEntry,
+                ..Default::default()
+            },
+            selected: Selection::PopLauncher(0),
+            cache: Cache::new(Database::in_memory().unwrap()),
+            pop_search,
+            script_results: vec![],
+            calc_result: None,
+            marked: vec![],
+            batch: BatchActivation::default(),
+            recall: None,
+            alias: None,
+            pending_alias: None,
+            scroll: Default::default(),
+            exec_on_next_search: false,
+            plugin_matchers: PluginConfigCache {
+                inner: HashMap::new(),
+            },
+            backend: BackendStatus::default(),
+        };
+
+        let onagre = Onagre {
+            state,
+            request_tx: Some(request_tx),
+        };
+
+        (onagre, request_rx)
+    }
+
+    fn activated(request_rx: &mut Receiver<Request>) -> Vec<u32> {
+        let mut ids = vec![];
+        while let Ok(Some(request)) = request_rx.try_next() {
+            if let Request::Activate(id) = request {
+                ids.push(id);
+            }
+        }
+
+        ids
+    }
+
+    #[test]
+    fn should_toggle_mark_on_selected_row() {
+        let (mut onagre, _request_rx) = onagre(3);
+
+        onagre.toggle_mark();
+        let _ = onagre.inc_selected();
+        let _ = onagre.inc_selected();
+        onagre.toggle_mark();
+        assert_eq!(onagre.state.marked, [0, 2]);
+
+        onagre.toggle_mark();
+        assert_eq!(onagre.state.marked, [0]);
+    }
+
+    #[test]
+    fn should_extend_marks_while_moving() {
+        let (mut onagre, _request_rx) = onagre(3);
+
+        let _ = onagre.extend_marked(KeyCode::Down);
+        let _ = onagre.extend_marked(KeyCode::Down);
+        assert_eq!(onagre.state.marked, [0, 1, 2]);
+        assert_eq!(onagre.selected(), Some(2));
+
+        let _ = onagre.extend_marked(KeyCode::Up);
+        assert_eq!(onagre.state.marked, [0, 1, 2]);
+    }
+
+    #[test]
+    fn should_activate_marked_rows_one_at_a_time() {
+        let (mut onagre, mut request_rx) = onagre(3);
+        onagre.state.marked = vec![2, 0, 1];
+
+        let _ = onagre.activate_marked();
+        assert_eq!(activated(&mut request_rx), [12]);
+        assert!(onagre.state.marked.is_empty());
+
+        // The next row is only activated once the previous one was answered
+        let _ = onagre.on_pop_launcher_message(SubscriptionMessage::PopMessage(Response::Close));
+        assert_eq!(activated(&mut request_rx), [10]);
+
+        // A plugin answering with new results waits for more input
+        let update = Response::Update(vec![]);
+        let _ = onagre.on_pop_launcher_message(SubscriptionMessage::PopMessage(update));
+        assert_eq!(activated(&mut request_rx), Vec::<u32>::new());
+        assert!(!onagre.state.batch.is_pending());
+    }
+
+    #[test]
+    fn should_skip_unanswered_activations() {
+        let (mut onagre, mut request_rx) = onagre(3);
+        onagre.state.marked = vec![0, 1];
+
+        let _ = onagre.activate_marked();
+        assert_eq!(activated(&mut request_rx), [10]);
+
+        // Stale timeouts are ignored
+        let _ = onagre.update(Message::ActivationTimeout(0));
+        assert_eq!(activated(&mut request_rx), Vec::<u32>::new());
+
+        let _ = onagre.update(Message::ActivationTimeout(1));
+        assert_eq!(activated(&mut request_rx), [11]);
+    }
+
+    #[test]
+    fn should_exit_once_marked_rows_are_answered() {
+        let mut batch = BatchActivation::default();
+        let activate = |id, generation| BatchStep::Activate { id, generation };
+
+        assert_eq!(batch.start([10, 11]), activate(10, 1));
+        assert_eq!(batch.on_launched(), activate(11, 2));
+        assert_eq!(batch.on_timeout(2), BatchStep::Exit);
+
+        // Outside of a batch, an answer ends the single activation
+        assert_eq!(batch.on_launched(), BatchStep::Exit);
+
+        // Nothing was launched
+        assert_eq!(batch.start([12]), activate(12, 4));
+        assert_eq!(batch.on_timeout(4), BatchStep::Stay);
+    }
+
+    #[test]
+    fn should_cycle_through_matching_queries() {
+        let (mut onagre, _request_rx) = onagre(0);
+        for (query, last_used) in [("cargo build", 3), ("htop", 2), ("cargo test", 1)] {
+            let entity = QueryEntity {
+                query: Cow::Borrowed(query),
+                weight: 0,
+                last_used,
+            };
+            onagre
+                .state
+                .cache
+                .db
+                .insert(query::COLLECTION, &entity)
+                .unwrap();
+        }
+
+        let _ = onagre.on_input_changed("cargo".to_string());
+        let mut recalled = vec![];
+        for _ in 0..3 {
+            let _ = onagre.recall_previous();
+            recalled.push(onagre.state.get_input());
+        }
+
+        // Most recent first, the last match stays once the history is exhausted
+        assert_eq!(recalled, ["cargo build", "cargo test", "cargo test"]);
+
+        // Editing the input starts over
+        let _ = onagre.on_input_changed("h".to_string());
+        let _ = onagre.recall_previous();
+        assert_eq!(onagre.state.get_input(), "htop");
+
+        // Submitting a recalled query doesn't count it twice
+        let _ = onagre.on_execute();
+        let htop = onagre
+            .state
+            .cache
+            .db
+            .get_by_key::<QueryEntity>(query::COLLECTION, "htop");
+        assert_eq!(htop.map(|entity| entity.weight), Some(0));
+    }
+}
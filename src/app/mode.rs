@@ -5,6 +5,9 @@ use once_cell::sync::Lazy;
 pub(crate) static WEB_CONFIG: Lazy<WebConfig> =
     Lazy::new(onagre_launcher_toolkit::plugins::web::load);
 
+// Plugin name of the native calculator, also used as its history collection
+pub(crate) const CALCULATOR: &str = "calculator";
+
 #[derive(Debug, PartialEq, Clone, Default)]
 pub enum ActiveMode {
     #[default]
@@ -18,6 +21,14 @@ pub enum ActiveMode {
         modifier: String,
         history: bool,
     },
+    Script {
+        plugin_name: String,
+        modifier: String,
+        history: bool,
+    },
+    Calc {
+        modifier: String,
+    },
 }
 
 impl From<QueryData> for ActiveMode {
@@ -27,6 +38,14 @@ impl From<QueryData> for ActiveMode {
             "web" => ActiveMode::Web {
                 modifier: query_data.modifier,
             },
+            CALCULATOR => ActiveMode::Calc {
+                modifier: query_data.modifier,
+            },
+            _script if query_data.script => ActiveMode::Script {
+                plugin_name: query_data.plugin_name,
+                modifier: query_data.modifier,
+                history: query_data.history,
+            },
             _other => ActiveMode::Plugin {
                 plugin_name: query_data.plugin_name,
                 modifier: query_data.modifier,
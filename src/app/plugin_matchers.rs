@@ -1,5 +1,6 @@
-use crate::app::mode::WEB_CONFIG;
+use crate::app::mode::{CALCULATOR, WEB_CONFIG};
 use crate::icons::IconPath;
+use crate::SETTINGS;
 use regex::Regex;
 
 #[derive(Debug, Clone)]
@@ -9,6 +10,8 @@ pub struct Plugin {
     pub history: bool,
     pub help: Option<String>,
     pub regex: Option<Regex>,
+    // Onagre script plugin, not handled by pop-launcher
+    pub script: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -18,6 +21,7 @@ pub struct QueryData {
     pub modifier: String,
     pub query: String,
     pub history: bool,
+    pub script: bool,
 }
 
 impl QueryData {
@@ -28,6 +32,18 @@ impl QueryData {
             modifier: modifier.as_ref().to_string(),
             history: true,
             query,
+            script: false,
+        }
+    }
+
+    fn new_mode_calculator<S: AsRef<str>>(modifier: S, query: String) -> QueryData {
+        QueryData {
+            icon: None,
+            plugin_name: CALCULATOR.to_string(),
+            modifier: modifier.as_ref().to_string(),
+            history: true,
+            query,
+            script: false,
         }
     }
 }
@@ -40,6 +56,7 @@ impl Plugin {
             modifier: self.name.clone(),
             query,
             history: self.history,
+            script: self.script,
         }
     }
 
@@ -50,13 +67,15 @@ impl Plugin {
             modifier: modifier.as_ref().to_string(),
             query,
             history: self.history,
+            script: self.script,
         }
     }
 }
 
+// Web shortcuts from onagre config are matched along the pop-launcher web plugin ones
 pub fn match_web_plugins(text: &str) -> Option<QueryData> {
     text.split_once(' ').and_then(|(mode, query)| {
-        if WEB_CONFIG.get(mode).is_some() {
+        if SETTINGS.web_shortcut(mode).is_some() || WEB_CONFIG.get(mode).is_some() {
             Some(QueryData::new_mode_web(mode, query.to_string()))
         } else {
             None
@@ -64,6 +83,17 @@ pub fn match_web_plugins(text: &str) -> Option<QueryData> {
     })
 }
 
+// The native calculator takes precedence over the pop-launcher `calc` plugin
+pub fn match_calculator(text: &str) -> Option<QueryData> {
+    let prefix = &SETTINGS.calculator.prefix;
+    if prefix.is_empty() {
+        return None;
+    }
+
+    text.strip_prefix(prefix.as_str())
+        .map(|query| QueryData::new_mode_calculator(prefix, query.to_string()))
+}
+
 impl Plugin {
     pub fn try_match(&self, text: &str) -> Option<QueryData> {
         self.match_plugin_help(text)
@@ -119,6 +149,7 @@ mod test {
             history: false,
             help: Some("find ".to_string()),
             regex: Some(Regex::new("^(find )+").unwrap()),
+            script: false,
         };
 
         let match_ = plugin.try_match("find some text");
@@ -131,6 +162,7 @@ mod test {
                 modifier: "find".to_string(),
                 query: " some text".to_string(),
                 history: false,
+                script: false,
             })
         );
     }
@@ -143,6 +175,7 @@ mod test {
             history: false,
             help: Some("find ".to_string()),
             regex: Some(Regex::new("^(find )+").unwrap()),
+            script: false,
         };
 
         let match_ = plugin.try_match("fin");
@@ -158,6 +191,7 @@ mod test {
             history: false,
             help: Some("find ".to_string()),
             regex: Some(Regex::new("^(find )+").unwrap()),
+            script: false,
         };
 
         let match_ = plugin.try_match("find ");
@@ -170,6 +204,7 @@ mod test {
                 modifier: "find".to_string(),
                 query: " ".to_string(),
                 history: false,
+                script: false,
             })
         );
     }
@@ -0,0 +1,164 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, error};
+
+use crate::settings::{ScriptInput, ScriptPluginConfig};
+
+// Script plugins taking longer than this are killed and return no results
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// A single result printed by a script plugin, either as a plain line
+// or as a json object
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScriptEntry {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    // Passed to the activation command, defaults to the name
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+impl ScriptEntry {
+    pub fn value(&self) -> &str {
+        self.value.as_deref().unwrap_or(&self.name)
+    }
+}
+
+// Run the plugin command for the given query and collect its results
+pub async fn search(plugin: ScriptPluginConfig, query: String) -> (String, Vec<ScriptEntry>) {
+    let entries = match run_search(&plugin, &query).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Script plugin '{}' failed: {err}", plugin.name);
+            vec![]
+        }
+    };
+
+    (query, entries)
+}
+
+async fn run_search(plugin: &ScriptPluginConfig, query: &str) -> anyhow::Result<Vec<ScriptEntry>> {
+    let has_placeholder = plugin.command.contains("{query}");
+    let mut args = substitute(&plugin.command, "{query}", query)?;
+    if plugin.input == ScriptInput::Argument && !has_placeholder {
+        args.push(query.to_string());
+    }
+
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("empty command"))?;
+
+    debug!(
+        "Running script plugin '{}': {program} {args:?}",
+        plugin.name
+    );
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        if plugin.input == ScriptInput::Stdin {
+            stdin.write_all(query.as_bytes()).await?;
+        }
+    }
+
+    let output = tokio::time::timeout(SCRIPT_TIMEOUT, child.wait_with_output()).await??;
+    Ok(parse_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Run the activation command for the selected entry, detached from onagre
+pub fn activate(plugin: &ScriptPluginConfig, entry: &ScriptEntry) -> anyhow::Result<()> {
+    let args = substitute(&plugin.activate, "{value}", entry.value())?;
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("empty activation command"))?;
+
+    debug!(
+        "Activating script plugin '{}': {program} {args:?}",
+        plugin.name
+    );
+    std::process::Command::new(program).args(args).spawn()?;
+    Ok(())
+}
+
+// Split the command line and replace the placeholder in each argument,
+// so values containing whitespaces stay a single argument
+fn substitute(command: &str, placeholder: &str, value: &str) -> anyhow::Result<Vec<String>> {
+    Ok(shell_words::split(command)?
+        .into_iter()
+        .map(|arg| arg.replace(placeholder, value))
+        .collect())
+}
+
+// Each non empty line is either a json object or a plain result name
+fn parse_output(output: &str) -> Vec<ScriptEntry> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if line.starts_with('{') {
+                if let Ok(entry) = serde_json::from_str(line) {
+                    return entry;
+                }
+            }
+
+            ScriptEntry {
+                name: line.to_string(),
+                description: None,
+                icon: None,
+                value: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::app::scripts::{parse_output, substitute, ScriptEntry};
+
+    #[test]
+    fn should_parse_plain_lines_and_json_objects() {
+        let output = r#"
+first
+{"name": "second", "description": "desc", "value": "2"}
+"#;
+
+        let entries = parse_output(output);
+
+        assert_eq!(
+            entries,
+            vec![
+                ScriptEntry {
+                    name: "first".to_string(),
+                    description: None,
+                    icon: None,
+                    value: None,
+                },
+                ScriptEntry {
+                    name: "second".to_string(),
+                    description: Some("desc".to_string()),
+                    icon: None,
+                    value: Some("2".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_keep_substituted_value_as_a_single_argument() {
+        let args = substitute("notify-send '{value}' done", "{value}", "hello world");
+
+        assert_eq!(args.unwrap(), vec!["notify-send", "hello world", "done"]);
+    }
+}
@@ -1,14 +1,21 @@
 use crate::app::cache::Cache;
+use crate::app::entries::alias_entry::AliasEntry;
 use crate::app::mode::ActiveMode;
-use crate::app::plugin_matchers::{match_web_plugins, Plugin};
+use crate::app::plugin_matchers::{match_calculator, match_web_plugins, Plugin};
+use crate::app::scripts::ScriptEntry;
 use onagre_launcher_toolkit::launcher::SearchResult;
-use tracing::debug;
+use regex::Regex;
+use tracing::{debug, error, warn};
 
 use crate::app::{Message, INPUT_ID};
 use crate::icons::IconPath;
-use crate::THEME;
+use crate::settings::AliasTarget;
+use crate::{SETTINGS, THEME};
 use iced::widget::{scrollable, text_input};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+
+// Number of backend stderr lines kept for the diagnostics view
+const MAX_DIAGNOSTICS: usize = 200;
 
 #[derive(Debug)]
 pub struct State<'a> {
@@ -16,9 +23,134 @@ pub struct State<'a> {
     pub selected: Selection,
     pub cache: Cache<'a>,
     pub pop_search: Vec<SearchResult>,
+    pub script_results: Vec<ScriptEntry>,
+    // Result of the current calculator expression, if it evaluates
+    pub calc_result: Option<String>,
+    // Indexes of the rows marked for batch activation, in marking order
+    pub marked: Vec<usize>,
+    // Marked rows being activated one after the other
+    pub batch: BatchActivation,
+    // Set while browsing previous queries
+    pub recall: Option<QueryRecall>,
+    // The alias exactly matching the input
+    pub alias: Option<AliasEntry>,
+    // Set while typing the keyword of a new alias
+    pub pending_alias: Option<AliasTarget>,
     pub scroll: scrollable::State,
     pub exec_on_next_search: bool,
     pub plugin_matchers: PluginConfigCache,
+    pub backend: BackendStatus,
+}
+
+#[derive(Debug)]
+pub struct QueryRecall {
+    // Only previous queries containing this term are recalled
+    pub term: String,
+    // Position in the matching queries, zero being the most recent
+    pub position: usize,
+}
+
+// Pop-launcher answers don't say which activation they belong to,
+// marked rows are activated one at a time so each answer matches the awaited one
+#[derive(Debug, Default)]
+pub struct BatchActivation {
+    // Pop-launcher ids not activated yet, in marking order
+    queue: VecDeque<u32>,
+    // The activation waiting for an answer
+    awaiting: Option<u32>,
+    // Identifies the awaited activation, older timeouts are ignored
+    generation: u64,
+    // Number of activations that launched something
+    launched: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BatchStep {
+    // Send an activation request for this pop-launcher id
+    Activate { id: u32, generation: u64 },
+    // Everything was launched, onagre can close
+    Exit,
+    // Nothing launched, keep the window open
+    Stay,
+}
+
+impl BatchActivation {
+    pub fn start(&mut self, ids: impl IntoIterator<Item = u32>) -> BatchStep {
+        self.queue = ids.into_iter().collect();
+        self.launched = 0;
+        self.advance()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.awaiting.is_some()
+    }
+
+    // Pop-launcher launched the awaited entry or asked to close,
+    // outside of a batch this ends a single activation
+    pub fn on_launched(&mut self) -> BatchStep {
+        if !self.is_pending() {
+            return BatchStep::Exit;
+        }
+
+        self.launched += 1;
+        self.advance()
+    }
+
+    // The awaited activation was not answered in time, move on to the next row
+    pub fn on_timeout(&mut self, generation: u64) -> BatchStep {
+        if !self.is_pending() || generation != self.generation {
+            return BatchStep::Stay;
+        }
+
+        warn!("Activation {:?} was not answered", self.awaiting);
+        self.advance()
+    }
+
+    // The plugin answered with results or a completion and waits for more input,
+    // the remaining rows are dropped
+    pub fn abort(&mut self) {
+        if self.is_pending() {
+            debug!(
+                "Batch activation interrupted, {} rows left",
+                self.queue.len()
+            );
+        }
+
+        self.queue.clear();
+        self.awaiting = None;
+    }
+
+    fn advance(&mut self) -> BatchStep {
+        self.awaiting = self.queue.pop_front();
+        self.generation += 1;
+        match self.awaiting {
+            Some(id) => BatchStep::Activate {
+                id,
+                generation: self.generation,
+            },
+            None if self.launched > 0 => BatchStep::Exit,
+            None => BatchStep::Stay,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BackendStatus {
+    // Displayed above the rows after the backend was restarted
+    pub notice: Option<String>,
+    // Backend stderr output, displayed in place of the rows when enabled
+    pub diagnostics: VecDeque<String>,
+    pub show_diagnostics: bool,
+}
+
+impl BackendStatus {
+    pub fn push_diagnostic(&mut self, line: String) {
+        if self.diagnostics.len() >= MAX_DIAGNOSTICS {
+            self.diagnostics.pop_front();
+        }
+
+        self.diagnostics.push_back(line);
+    }
 }
 
 #[derive(Debug)]
@@ -52,11 +184,40 @@ impl PluginConfigCache {
                 history: config.history,
                 help: config.query.help.map(|h| h.to_string()),
                 regex,
+                script: false,
             };
 
             cache.insert(name, plugin);
         }
 
+        for config in &SETTINGS.script_plugins {
+            let regex = match Regex::new(&format!("^{}", regex::escape(&config.prefix))) {
+                Ok(regex) => regex,
+                Err(err) => {
+                    error!("Invalid prefix for script plugin '{}': {err}", config.name);
+                    continue;
+                }
+            };
+
+            let icon = THEME.icon_theme.as_ref().and_then(|theme| {
+                config
+                    .icon
+                    .as_ref()
+                    .and_then(|icon| IconPath::from_name_or_path(icon, theme))
+            });
+
+            let plugin = Plugin {
+                name: config.name.clone(),
+                icon,
+                history: config.history,
+                help: Some(config.prefix.clone()),
+                regex: Some(regex),
+                script: true,
+            };
+
+            cache.insert(config.name.clone(), plugin);
+        }
+
         PluginConfigCache { inner: cache }
     }
     pub fn get_plugin_icon(&self, plugin_name: &str) -> Option<IconPath> {
@@ -96,13 +257,15 @@ impl State<'_> {
 
     pub fn with_mode(mode_query: &str) -> Self {
         let plugin_matchers = PluginConfigCache::load();
-        let plugin_split = match_web_plugins(mode_query).or_else(|| {
-            plugin_matchers
-                .inner
-                .values()
-                .map(|matcher| matcher.try_match(mode_query))
-                .find_map(|match_| match_)
-        });
+        let plugin_split = match_calculator(mode_query)
+            .or_else(|| match_web_plugins(mode_query))
+            .or_else(|| {
+                plugin_matchers
+                    .inner
+                    .values()
+                    .map(|matcher| matcher.try_match(mode_query))
+                    .find_map(|match_| match_)
+            });
 
         let mode = plugin_split
             .as_ref()
@@ -120,6 +283,13 @@ impl State<'_> {
             selected: Selection::History(0),
             cache: Default::default(),
             pop_search: Default::default(),
+            script_results: Default::default(),
+            calc_result: None,
+            marked: vec![],
+            batch: BatchActivation::default(),
+            recall: None,
+            alias: None,
+            pending_alias: None,
             scroll: Default::default(),
             input_value: SearchInput {
                 mode,
@@ -129,6 +299,7 @@ impl State<'_> {
             },
             exec_on_next_search: false,
             plugin_matchers,
+            backend: BackendStatus::default(),
         }
     }
 
@@ -148,7 +319,9 @@ impl State<'_> {
             ActiveMode::Web { modifier, .. } => {
                 format!("{modifier} {}", self.input_value.input_display)
             }
-            ActiveMode::Plugin { modifier, .. } => {
+            ActiveMode::Plugin { modifier, .. }
+            | ActiveMode::Script { modifier, .. }
+            | ActiveMode::Calc { modifier } => {
                 format!("{modifier}{}", self.input_value.input_display)
             }
         };
@@ -160,14 +333,22 @@ impl State<'_> {
         );
     }
 
+    // The query for an onagre handled plugin, without its prefix
+    pub fn plugin_query(&self, prefix: &str) -> String {
+        let input = &self.input_value.input_display;
+        input.strip_prefix(prefix).unwrap_or(input).to_string()
+    }
+
     fn set_input_without_modifier(&mut self, input: &str) {
-        let plugin_split = match_web_plugins(input).or_else(|| {
-            self.plugin_matchers
-                .inner
-                .values()
-                .map(|matcher| matcher.try_match(input))
-                .find_map(|match_| match_)
-        });
+        let plugin_split = match_calculator(input)
+            .or_else(|| match_web_plugins(input))
+            .or_else(|| {
+                self.plugin_matchers
+                    .inner
+                    .values()
+                    .map(|matcher| matcher.try_match(input))
+                    .find_map(|match_| match_)
+            });
 
         if let Some(query_data) = plugin_split {
             self.input_value.modifier_display = query_data.modifier.clone();
@@ -220,10 +401,18 @@ impl Default for State<'_> {
             selected: Selection::History(0),
             cache: Default::default(),
             pop_search: Default::default(),
+            script_results: Default::default(),
+            calc_result: None,
+            marked: vec![],
+            batch: BatchActivation::default(),
+            recall: None,
+            alias: None,
+            pending_alias: None,
             scroll: Default::default(),
             input_value: SearchInput::default(),
             exec_on_next_search: false,
             plugin_matchers: PluginConfigCache::load(),
+            backend: BackendStatus::default(),
         }
     }
 }
@@ -32,12 +32,12 @@ pub struct AppContainerStyles {
 
 impl Scale for AppContainerStyles {
     fn scale(mut self, scale: f32) -> Self {
+        self.padding = self.padding.scale(scale);
+        self.border_radius = self.border_radius.scale(scale);
+        self.border_width = self.border_width.scale(scale);
         self.search = self.search.scale(scale);
-        self.padding = self.padding * scale;
         self.rows = self.rows.scale(scale);
-        self.search = self.search.scale(scale);
         self.scrollable = self.scrollable.scale(scale);
-        self.border_width = self.border_width.scale(scale);
         self
     }
 }
@@ -10,7 +10,7 @@ use crate::THEME_SCALE;
 use iced::widget::container::Appearance;
 use iced::Background;
 use iced_core::{BorderRadius, Length};
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 pub mod app;
 pub mod rows;
@@ -27,14 +27,45 @@ impl Theme {
         };
 
         let mut theme = theme.unwrap_or_default();
-        if let Some(scale) = THEME_SCALE.get() {
-            theme = theme.scale(*scale)
+        // `--scale` takes precedence over the environment
+        let scale = THEME_SCALE
+            .get()
+            .copied()
+            .unwrap_or_else(|| environment_scale(|key| std::env::var(key).ok()));
+
+        if scale != 1.0 {
+            info!("Using scale value : {scale}");
+            theme = theme.scale(scale)
         }
 
         theme
     }
 }
 
+// The HiDPI scale GTK and Qt applications are asked to use, an X11 only override.
+// On Wayland, or when winit is given an X11 scale factor, iced already applies the
+// display scale and dimensions are logical pixels, scaling again would double it.
+fn environment_scale<F>(var: F) -> f32
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if var("WAYLAND_DISPLAY").is_some() || var("WINIT_X11_SCALE_FACTOR").is_some() {
+        return 1.0;
+    }
+
+    let factor = |key: &str| {
+        var(key)
+            .and_then(|value| value.trim().parse::<f32>().ok())
+            .filter(|value| value.is_finite() && *value > 0.0)
+    };
+
+    match (factor("GDK_SCALE"), factor("QT_SCALE_FACTOR")) {
+        (Some(gdk_scale), _) => gdk_scale * factor("GDK_DPI_SCALE").unwrap_or(1.0),
+        (None, Some(qt_scale)) => qt_scale,
+        (None, None) => 1.0,
+    }
+}
+
 pub(crate) trait Scale {
     fn scale(self, scale: f32) -> Self;
 }
@@ -52,9 +83,12 @@ pub struct Theme {
     pub size: (u32, u32),
     pub font: Option<String>,
     pub font_size: u16,
-    pub icon_theme: Option<String>,
+    // Icon themes tried in order, `None` disables icons
+    pub icon_theme: Option<Vec<String>>,
     pub icon_size: u16,
     pub padding: OnagrePadding,
+    // The scale applied to every dimension, used to pick icons for HiDPI screens
+    pub scale: f32,
 
     // Style
     pub background: OnagreColor,
@@ -70,11 +104,14 @@ pub struct Theme {
 impl Scale for Theme {
     fn scale(mut self, scale: f32) -> Self {
         self.app_container = self.app_container.scale(scale);
-        self.icon_size = (self.icon_size as f32 * scale) as u16;
+        self.icon_size = self.icon_size.scale(scale);
         self.size.0 = (self.size.0 as f32 * scale) as u32;
         self.size.1 = (self.size.1 as f32 * scale) as u32;
-        self.padding = self.padding * scale;
-        self.font_size = (self.font_size as f32 * scale) as u16;
+        self.padding = self.padding.scale(scale);
+        self.font_size = self.font_size.scale(scale);
+        self.border_radius = self.border_radius.scale(scale);
+        self.border_width = self.border_width.scale(scale);
+        self.scale *= scale;
         self
     }
 }
@@ -94,6 +131,12 @@ impl Scale for u16 {
     }
 }
 
+impl<T: Scale> Scale for Option<T> {
+    fn scale(self, scale: f32) -> Self {
+        self.map(|value| value.scale(scale))
+    }
+}
+
 impl Scale for f32 {
     fn scale(self, scale: f32) -> Self {
         self * scale
@@ -129,9 +172,10 @@ impl Default for Theme {
             size: (450, 300),
             font: None,
             font_size: 18,
-            // TODO: default icon theme ?
-            icon_theme: Some("Papirus".to_string()),
+            // Use the system icon theme, see `freedesktop::icon_theme`
+            icon_theme: Some(vec![]),
             icon_size: 24,
+            scale: 1.0,
             background: OnagreColor::DEFAULT_BACKGROUND,
             color: OnagreColor::DEFAULT_TEXT,
             border_color: OnagreColor::TRANSPARENT,
@@ -156,3 +200,83 @@ impl iced::widget::container::StyleSheet for &Theme {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::app::style::rows::generic::GenericContainerStyle;
+    use crate::app::style::{environment_scale, Scale, Theme};
+    use crate::config::padding::OnagrePadding;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn should_scale_every_dimension_once() {
+        let mut theme = Theme::default();
+        theme.app_container.search.plugin_hint = Some(GenericContainerStyle::default());
+        let hint_font_size = GenericContainerStyle::default().font_size;
+
+        let theme = theme.scale(2.0);
+        let search = theme.search();
+
+        assert_eq!(theme.scale, 2.0);
+        assert_eq!(search.border_radius, 8.0);
+        assert_eq!(search.input.font_size, 28);
+        assert_eq!(
+            search.input.padding,
+            OnagrePadding {
+                top: 0,
+                right: 10,
+                bottom: 0,
+                left: 10,
+            }
+        );
+        assert_eq!(
+            theme.plugin_hint().map(|hint| hint.font_size),
+            Some(hint_font_size * 2)
+        );
+    }
+
+    #[test]
+    fn should_read_scale_from_environment() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                vars.iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        asserting!("Should default to no scaling")
+            .that(&environment_scale(env(&[])))
+            .is_equal_to(1.0);
+
+        asserting!("Should combine GDK scales")
+            .that(&environment_scale(env(&[
+                ("GDK_SCALE", "2"),
+                ("GDK_DPI_SCALE", "0.5"),
+                ("QT_SCALE_FACTOR", "3"),
+            ])))
+            .is_equal_to(1.0);
+
+        asserting!("Should use the Qt scale factor")
+            .that(&environment_scale(env(&[("QT_SCALE_FACTOR", "1.5")])))
+            .is_equal_to(1.5);
+
+        asserting!("Should ignore invalid values")
+            .that(&environment_scale(env(&[("GDK_SCALE", "-2")])))
+            .is_equal_to(1.0);
+
+        asserting!("Should leave Wayland scaling to the compositor")
+            .that(&environment_scale(env(&[
+                ("WAYLAND_DISPLAY", "wayland-1"),
+                ("GDK_SCALE", "2"),
+            ])))
+            .is_equal_to(1.0);
+
+        asserting!("Should leave X11 scaling to winit when it is set")
+            .that(&environment_scale(env(&[
+                ("WINIT_X11_SCALE_FACTOR", "2"),
+                ("QT_SCALE_FACTOR", "2"),
+            ])))
+            .is_equal_to(1.0);
+    }
+}
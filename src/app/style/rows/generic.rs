@@ -29,6 +29,7 @@ impl Scale for GenericContainerStyle {
         self.height = self.height.scale(scale);
         self.width = self.width.scale(scale);
         self.font_size = self.font_size.scale(scale);
+        self.border_radius = self.border_radius.scale(scale);
         self.border_width = self.border_width.scale(scale);
         self.padding = self.padding.scale(scale);
         self
@@ -27,6 +27,7 @@ impl Scale for IconStyle {
         self.height = self.height.scale(scale);
         self.width = self.width.scale(scale);
         self.padding = self.padding.scale(scale);
+        self.border_radius = self.border_radius.scale(scale);
         self.border_width = self.border_width.scale(scale);
         self.icon_size = self.icon_size.scale(scale);
         self
@@ -43,7 +43,9 @@ impl Scale for RowStyles {
     fn scale(mut self, scale: f32) -> Self {
         self.height = self.height.scale(scale);
         self.width = self.width.scale(scale);
+        self.padding = self.padding.scale(scale);
         self.spacing = self.spacing.scale(scale);
+        self.border_radius = self.border_radius.scale(scale);
         self.border_width = self.border_width.scale(scale);
         self.title = self.title.scale(scale);
         self.description = self.description.scale(scale);
@@ -105,5 +107,13 @@ impl RowStyles {
             ..Default::default()
         }
     }
+
+    pub fn default_marked() -> Self {
+        Self {
+            border_width: 1.0,
+            border_color: OnagreColor::DEFAULT_BORDER,
+            ..Default::default()
+        }
+    }
 }
 impl Eq for RowStyles {}
@@ -25,16 +25,23 @@ pub struct RowContainerStyle {
     // Iced Scrollable
     pub row: RowStyles,
     pub row_selected: RowStyles,
+    // Rows marked for batch activation
+    pub row_marked: RowStyles,
+    // Pinned history entries
+    pub row_pinned: RowStyles,
 }
 
 impl Scale for RowContainerStyle {
     fn scale(mut self, scale: f32) -> Self {
         self.padding = self.padding.scale(scale);
+        self.border_radius = self.border_radius.scale(scale);
         self.border_width = self.border_width.scale(scale);
         self.width = self.width.scale(scale);
         self.height = self.height.scale(scale);
         self.row = self.row.scale(scale);
         self.row_selected = self.row_selected.scale(scale);
+        self.row_marked = self.row_marked.scale(scale);
+        self.row_pinned = self.row_pinned.scale(scale);
         self
     }
 }
@@ -73,6 +80,8 @@ impl Default for RowContainerStyle {
             height: Length::FillPortion(8),
             row: RowStyles::default(),
             row_selected: RowStyles::default_selected(),
+            row_marked: RowStyles::default_marked(),
+            row_pinned: RowStyles::default(),
         }
     }
 }
@@ -23,7 +23,9 @@ pub struct ScrollerStyles {
 
 impl Scale for ScrollerStyles {
     fn scale(mut self, scale: f32) -> Self {
+        self.border_radius = self.border_radius.scale(scale);
         self.border_width = self.border_width.scale(scale);
+        self.scroller_border_radius = self.scroller_border_radius.scale(scale);
         self.scroller_border_width = self.scroller_border_width.scale(scale);
         self.scrollbar_margin = self.scrollbar_margin.scale(scale);
         self.scrollbar_width = self.scrollbar_width.scale(scale);
@@ -31,9 +31,11 @@ impl Scale for SearchInputStyles {
     fn scale(mut self, scale: f32) -> Self {
         self.height = self.height.scale(scale);
         self.width = self.width.scale(scale);
+        self.text_width = self.text_width.scale(scale);
         self.padding = self.padding.scale(scale);
-        self.padding = self.padding.scale(scale);
-        self.padding = self.padding.scale(scale);
+        self.font_size = self.font_size.scale(scale);
+        self.border_radius = self.border_radius.scale(scale);
+        self.border_width = self.border_width.scale(scale);
         self
     }
 }
@@ -38,11 +38,13 @@ pub struct SearchContainerStyles {
 impl Scale for SearchContainerStyles {
     fn scale(mut self, scale: f32) -> Self {
         self.padding = self.padding.scale(scale);
+        self.border_radius = self.border_radius.scale(scale);
         self.border_width = self.border_width.scale(scale);
         self.spacing = self.spacing.scale(scale);
         self.width = self.width.scale(scale);
         self.height = self.height.scale(scale);
         self.input = self.input.scale(scale);
+        self.plugin_hint = self.plugin_hint.scale(scale);
         self
     }
 }
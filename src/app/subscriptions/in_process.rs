@@ -0,0 +1,97 @@
+use iced::futures::channel::mpsc;
+use iced::futures::channel::mpsc::{channel, Sender};
+use iced::futures::{SinkExt, StreamExt};
+use onagre_launcher_toolkit::launcher::{Request, Response};
+use onagre_launcher_toolkit::service::{self, Service};
+use tokio::task::LocalSet;
+use tracing::{debug, error};
+
+use crate::app::subscriptions::pop_launcher::{BackendExit, SubscriptionMessage};
+
+// Run the launcher service inside onagre instead of spawning `pop-launcher`.
+// The service gets its own single threaded runtime so plugin tasks never block the UI,
+// requests and responses go through channels instead of json lines.
+pub(crate) async fn run(
+    sender: &mut Sender<SubscriptionMessage>,
+    request_rx: &mut mpsc::Receiver<Request>,
+    last_search: &mut Option<Request>,
+    restarts: &mut u32,
+) -> BackendExit {
+    let (response_tx, mut response_rx) = channel::<Response>(32);
+    let (mut service_tx, service_rx) = channel::<Request>(32);
+
+    let spawned = std::thread::Builder::new()
+        .name("onagre-launcher-service".to_string())
+        .spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(err) => {
+                    error!("Failed to build the launcher service runtime: {err}");
+                    return;
+                }
+            };
+
+            // Recently used results, loaded the way pop-launcher does
+            let recent = service::ensure_cache_path()
+                .ok()
+                .and_then(|path| std::fs::File::open(path).ok())
+                .and_then(|file| serde_json::from_reader(file).ok())
+                .unwrap_or_default();
+
+            let service = Service::new(response_tx, recent);
+            LocalSet::new().block_on(&runtime, service.exec(service_rx));
+            debug!("In process launcher service stopped");
+        });
+
+    if let Err(err) = spawned {
+        error!("Failed to start the launcher service thread: {err}");
+        return BackendExit::Crashed;
+    }
+
+    // Replay the current query so the restarted service picks up where the previous one was
+    if let Some(search) = last_search.as_ref() {
+        if service_tx.send(search.clone()).await.is_err() {
+            return BackendExit::Crashed;
+        }
+    }
+
+    let forward_responses = async {
+        while let Some(response) = response_rx.next().await {
+            debug!("Got a response from the launcher service");
+            debug!("{:?}", response);
+            *restarts = 0;
+            if sender
+                .send(SubscriptionMessage::PopMessage(response))
+                .await
+                .is_err()
+            {
+                return BackendExit::Closed;
+            }
+        }
+
+        BackendExit::Crashed
+    };
+
+    let forward_requests = async {
+        while let Some(request) = request_rx.next().await {
+            if let Request::Search(_) = &request {
+                *last_search = Some(request.clone());
+            }
+
+            if service_tx.send(request).await.is_err() {
+                return BackendExit::Crashed;
+            }
+        }
+
+        BackendExit::Closed
+    };
+
+    // Dropping `service_tx` on return closes the service input and stops its thread
+    tokio::select! {
+        reason = forward_responses => reason,
+        reason = forward_requests => reason,
+    }
+}
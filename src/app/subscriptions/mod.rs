@@ -1 +1,2 @@
+pub mod in_process;
 pub mod pop_launcher;
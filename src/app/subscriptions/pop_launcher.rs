@@ -1,56 +1,276 @@
 use iced::futures::channel::mpsc;
 use iced::futures::channel::mpsc::{channel, Sender};
 use iced::futures::stream::BoxStream;
-use iced::futures::{join, SinkExt, StreamExt};
+use iced::futures::{SinkExt, StreamExt};
 use iced::Subscription;
 use iced_core::event::Status;
 use iced_runtime::futures::futures::stream;
 use iced_runtime::futures::subscription::Recipe;
 use onagre_launcher_toolkit::launcher::{json_input_stream, Request, Response};
+use std::fmt::Display;
 use std::hash::Hash;
 use std::process::{exit, Stdio};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{ChildStderr, ChildStdin, ChildStdout, Command};
-use tracing::{debug, error};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, Command};
+use tracing::{debug, error, warn};
+
+use crate::app::subscriptions::in_process;
+use crate::settings::Backend;
+use crate::SETTINGS;
+
+// Delay before the first restart attempt, doubled on each consecutive crash
+const RESTART_DELAY: Duration = Duration::from_millis(250);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(10);
+
+// Why a backend session ended
+#[derive(Debug, PartialEq)]
+pub(crate) enum BackendExit {
+    // pop-launcher exited, closed one of its pipes or wrote garbage, restart it
+    Crashed,
+    // Onagre dropped its end of the channels, nothing left to supervise
+    Closed,
+}
+
+// A closed stdout or a malformed line means the backend is gone
+fn classify_output<E: Display>(
+    output: Option<Result<Response, E>>,
+) -> Result<Response, BackendExit> {
+    match output {
+        Some(Ok(response)) => Ok(response),
+        Some(Err(err)) => {
+            error!("pop-launcher wrote malformed json: {err}");
+            Err(BackendExit::Crashed)
+        }
+        None => {
+            warn!("pop-launcher stdout closed");
+            Err(BackendExit::Crashed)
+        }
+    }
+}
 
 // Whenever a message is red from pop-launcher stdout, send it to the subscription receiver
-async fn handle_stdout(stdout: ChildStdout, mut sender: Sender<Response>) {
+async fn handle_stdout<R: AsyncRead + Unpin + Send>(
+    stdout: R,
+    sender: &mut Sender<SubscriptionMessage>,
+    restarts: &mut u32,
+) -> BackendExit {
     let mut stream = json_input_stream::<_, Response>(stdout);
 
-    while let Some(response) = stream.next().await {
+    loop {
+        let response = match classify_output(stream.next().await) {
+            Ok(response) => response,
+            Err(reason) => return reason,
+        };
+
         debug!("Got a response from pop-launcher");
         debug!("{:?}", response);
-        sender.send(response.unwrap()).await.unwrap();
+
+        // The backend is answering again, reset the back-off
+        *restarts = 0;
+
+        if sender
+            .send(SubscriptionMessage::PopMessage(response))
+            .await
+            .is_err()
+        {
+            return BackendExit::Closed;
+        }
     }
 }
 
-// Whenever a message is red from pop-launcher stderr, print it to onagre stderr
-async fn handle_stderr(stderr: ChildStderr) {
+// Whenever a message is red from pop-launcher stderr, forward it to the diagnostics view
+async fn handle_stderr(stderr: ChildStderr, mut sender: Sender<SubscriptionMessage>) {
     let mut lines = BufReader::new(stderr).lines();
 
-    while let Ok(line) = lines.next_line().await {
-        debug!("line : {}", line.unwrap());
+    while let Ok(Some(line)) = lines.next_line().await {
+        debug!("line : {}", line);
+        if sender
+            .send(SubscriptionMessage::Stderr(line))
+            .await
+            .is_err()
+        {
+            break;
+        }
     }
 }
 
 // Listen for incoming `pop_launcher::Request` from the receiver and forward them to
-// pop launcher stdin
-async fn handle_stdin(mut stdin: ChildStdin, mut request_rx: mpsc::Receiver<Request>) {
+// pop launcher stdin, keeping track of the last search so it can be replayed on restart
+async fn handle_stdin<W: AsyncWrite + Unpin>(
+    stdin: &mut W,
+    request_rx: &mut mpsc::Receiver<Request>,
+    last_search: &mut Option<Request>,
+) -> BackendExit {
     while let Some(request) = request_rx.next().await {
-        let request = serde_json::to_string(&request).unwrap();
-        let request = format!("{}\n", request);
-        stdin.write_all(request.as_bytes()).await.unwrap();
-        debug!("Wrote request {:?} to pop-launcher stdin", request);
-        stdin.flush().await.unwrap();
+        if let Request::Search(_) = &request {
+            *last_search = Some(request.clone());
+        }
+
+        if write_request(stdin, &request).await.is_err() {
+            error!("Failed to write request {request:?} to pop-launcher stdin");
+            return BackendExit::Crashed;
+        }
+    }
+
+    BackendExit::Closed
+}
+
+async fn write_request<W: AsyncWrite + Unpin>(
+    stdin: &mut W,
+    request: &Request,
+) -> std::io::Result<()> {
+    let request = serde_json::to_string(request)?;
+    let request = format!("{}\n", request);
+    stdin.write_all(request.as_bytes()).await?;
+    debug!("Wrote request {:?} to pop-launcher stdin", request);
+    stdin.flush().await
+}
+
+fn spawn_pop_launcher() -> std::io::Result<Child> {
+    Command::new("pop-launcher")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+}
+
+// Run a single pop-launcher process until it dies or onagre stops listening
+async fn run_backend(
+    mut child: Child,
+    sender: &mut Sender<SubscriptionMessage>,
+    request_rx: &mut mpsc::Receiver<Request>,
+    last_search: &mut Option<Request>,
+    restarts: &mut u32,
+) -> BackendExit {
+    let (Some(stdout), Some(stderr), Some(mut stdin)) =
+        (child.stdout.take(), child.stderr.take(), child.stdin.take())
+    else {
+        error!("Failed to acquire pop-launcher stdio");
+        return BackendExit::Crashed;
+    };
+
+    tokio::spawn(handle_stderr(stderr, sender.clone()));
+
+    if replay_search(&mut stdin, last_search).await.is_err() {
+        return BackendExit::Crashed;
+    }
+
+    let reason = tokio::select! {
+        reason = handle_stdout(stdout, sender, restarts) => reason,
+        reason = handle_stdin(&mut stdin, request_rx, last_search) => reason,
+    };
+
+    if let Err(err) = child.kill().await {
+        debug!("pop-launcher was already gone: {err}");
+    }
+
+    reason
+}
+
+// Keep a backend alive for the whole onagre session, restarting it with
+// an exponential back-off whenever it crashes.
+async fn supervise(
+    backend: Backend,
+    mut sender: Sender<SubscriptionMessage>,
+    mut request_rx: mpsc::Receiver<Request>,
+) {
+    let mut last_search = None;
+    let mut restarts = 0;
+    let mut started_once = false;
+
+    loop {
+        let reason = match backend {
+            Backend::Process => {
+                let child = match spawn_pop_launcher() {
+                    Ok(child) => child,
+                    Err(err) if !started_once => {
+                        error!("Failed to start pop-launcher backend: {err}");
+                        error!("Make sure either pop-launcher or onagre-launcher is installed.");
+                        error!("See: https://github.com/pop-os/launcher or https://github.com/onagre-launcher/launcher");
+                        exit(1);
+                    }
+                    Err(err) => {
+                        error!("Failed to restart pop-launcher backend: {err}");
+                        backoff(&mut restarts).await;
+                        continue;
+                    }
+                };
+
+                started_once = true;
+                run_backend(
+                    child,
+                    &mut sender,
+                    &mut request_rx,
+                    &mut last_search,
+                    &mut restarts,
+                )
+                .await
+            }
+            Backend::InProcess => {
+                in_process::run(
+                    &mut sender,
+                    &mut request_rx,
+                    &mut last_search,
+                    &mut restarts,
+                )
+                .await
+            }
+        };
+
+        if reason == BackendExit::Closed {
+            debug!("Onagre is gone, stopping backend supervision");
+            return;
+        }
+
+        warn!("Launcher backend died, restarting");
+        if sender
+            .send(SubscriptionMessage::BackendRestarted)
+            .await
+            .is_err()
+        {
+            return;
+        }
+
+        backoff(&mut restarts).await;
     }
 }
 
+// Replay the current query so the restarted backend picks up where the previous one was
+async fn replay_search<W: AsyncWrite + Unpin>(
+    stdin: &mut W,
+    last_search: &Option<Request>,
+) -> std::io::Result<()> {
+    match last_search {
+        Some(search) => write_request(stdin, search).await,
+        None => Ok(()),
+    }
+}
+
+fn restart_delay(restarts: u32) -> Duration {
+    RESTART_DELAY
+        .saturating_mul(2u32.saturating_pow(restarts))
+        .min(MAX_RESTART_DELAY)
+}
+
+async fn backoff(restarts: &mut u32) {
+    let delay = restart_delay(*restarts);
+    *restarts = restarts.saturating_add(1);
+    debug!("Waiting {delay:?} before restarting pop-launcher");
+    tokio::time::sleep(delay).await;
+}
+
 pub struct PopLauncherSubscription;
 
 #[derive(Debug, Clone)]
 pub enum SubscriptionMessage {
     Ready(Sender<Request>),
     PopMessage(Response),
+    // The backend crashed and a new one was started
+    BackendRestarted,
+    // A line written by the backend on stderr
+    Stderr(String),
 }
 
 impl PopLauncherSubscription {
@@ -68,36 +288,126 @@ impl Recipe for PopLauncherSubscription {
     }
 
     fn stream(self: Box<Self>, _: BoxStream<(iced::Event, Status)>) -> BoxStream<Self::Output> {
-        debug!("Starting `pop-launcher` subscription");
-        let Ok(child) = Command::new("pop-launcher")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-        else {
-            error!("Failed to start pop-launcher backend.");
-            error!("Make sure either pop-launcher or onagre-launcher is installed.");
-            error!("See: https://github.com/pop-os/launcher or https://github.com/onagre-launcher/launcher");
-            exit(1);
-        };
-
+        debug!(
+            "Starting `pop-launcher` subscription with {:?} backend",
+            SETTINGS.backend
+        );
         let (response_tx, response_rx) = channel(32);
         let (request_tx, request_rx) = channel(32);
 
-        let stdout = child.stdout.unwrap();
-        let stderr = child.stderr.unwrap();
-        let stdin = child.stdin.unwrap();
+        tokio::spawn(supervise(SETTINGS.backend, response_tx, request_rx));
+
+        Box::pin(stream::iter(vec![SubscriptionMessage::Ready(request_tx)]).chain(response_rx))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::app::subscriptions::pop_launcher::{
+        classify_output, handle_stdin, handle_stdout, replay_search, restart_delay, BackendExit,
+        SubscriptionMessage,
+    };
+    use iced::futures::channel::mpsc::channel;
+    use iced::futures::{SinkExt, StreamExt};
+    use onagre_launcher_toolkit::launcher::{Request, Response};
+    use std::time::Duration;
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn should_double_restart_delay_up_to_max() {
+        let delays: Vec<_> = [0, 1, 2, 5, 6, u32::MAX]
+            .iter()
+            .copied()
+            .map(restart_delay)
+            .collect();
+
+        assert_eq!(
+            delays,
+            [
+                Duration::from_millis(250),
+                Duration::from_millis(500),
+                Duration::from_secs(1),
+                Duration::from_secs(8),
+                Duration::from_secs(10),
+                Duration::from_secs(10),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_classify_backend_output() {
+        assert!(classify_output::<String>(Some(Ok(Response::Close))).is_ok());
+        assert_eq!(
+            classify_output(Some(Err("expected value"))).err(),
+            Some(BackendExit::Crashed)
+        );
+        assert_eq!(
+            classify_output::<String>(None).err(),
+            Some(BackendExit::Crashed)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reset_restarts_on_response() {
+        let (mut backend, stdout) = duplex(1024);
+        let (mut sender, mut receiver) = channel(8);
+        let mut restarts = 3;
+
+        backend.write_all(b"\"Close\"\nnot json\n").await.unwrap();
+        let reason = handle_stdout(stdout, &mut sender, &mut restarts).await;
+
+        assert_eq!(reason, BackendExit::Crashed);
+        assert_eq!(restarts, 0);
+        assert!(matches!(
+            receiver.next().await,
+            Some(SubscriptionMessage::PopMessage(Response::Close))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_stop_when_onagre_is_gone() {
+        let (mut backend, stdout) = duplex(1024);
+        let (mut sender, receiver) = channel(8);
+        drop(receiver);
+        let mut restarts = 0;
+
+        backend.write_all(b"\"Close\"\n").await.unwrap();
+        let reason = handle_stdout(stdout, &mut sender, &mut restarts).await;
+
+        assert_eq!(reason, BackendExit::Closed);
+    }
+
+    #[tokio::test]
+    async fn should_replay_last_search() {
+        let (mut stdin, mut backend) = duplex(1024);
+        let (mut request_tx, mut request_rx) = channel(8);
+        let mut last_search = None;
 
-        let stdout_handle = handle_stdout(stdout, response_tx);
-        let stderr_handle = handle_stderr(stderr);
-        let stdin_handle = handle_stdin(stdin, request_rx);
+        request_tx
+            .send(Request::Search("fire".to_string()))
+            .await
+            .unwrap();
+        request_tx.send(Request::Activate(0)).await.unwrap();
+        drop(request_tx);
 
-        tokio::spawn(async {
-            join!(stdout_handle, stderr_handle, stdin_handle);
-        });
+        let reason = handle_stdin(&mut stdin, &mut request_rx, &mut last_search).await;
+        assert_eq!(reason, BackendExit::Closed);
+        assert!(matches!(&last_search, Some(Request::Search(query)) if query == "fire"));
 
-        let pop_response_rx = response_rx.map(SubscriptionMessage::PopMessage);
+        // A restarted backend gets the last search first
+        let (mut restarted, mut restarted_backend) = duplex(1024);
+        replay_search(&mut restarted, &last_search).await.unwrap();
+        drop(restarted);
+        let mut replayed = String::new();
+        restarted_backend
+            .read_to_string(&mut replayed)
+            .await
+            .unwrap();
+        assert_eq!(replayed, "{\"Search\":\"fire\"}\n");
 
-        Box::pin(stream::iter(vec![SubscriptionMessage::Ready(request_tx)]).chain(pop_response_rx))
+        drop(stdin);
+        let mut written = String::new();
+        backend.read_to_string(&mut written).await.unwrap();
+        assert_eq!(written.lines().count(), 2);
     }
 }
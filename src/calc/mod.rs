@@ -0,0 +1,648 @@
+use std::f64::consts::{E, PI};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use thiserror::Error;
+
+use units::Unit;
+
+pub mod units;
+
+// A small calculator used by the native calculator mode.
+// It supports arithmetic, common functions, radix conversions (`255 to hex`)
+// and unit conversions (`10 km to mi`).
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CalcError {
+    #[error("Unexpected character '{0}'")]
+    UnexpectedChar(char),
+    #[error("Unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("Unexpected token '{0}'")]
+    UnexpectedToken(String),
+    #[error("Unknown function or constant '{0}'")]
+    Unknown(String),
+    #[error("Cannot convert {0} to {1}")]
+    IncompatibleUnits(String, String),
+    #[error("Only integers can be displayed as {0}")]
+    NotAnInteger(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Radix {
+    Hex,
+    Binary,
+    Octal,
+    Decimal,
+}
+
+impl Radix {
+    fn from_name(name: &str) -> Option<Radix> {
+        match name.to_lowercase().as_str() {
+            "hex" | "hexadecimal" => Some(Radix::Hex),
+            "bin" | "binary" => Some(Radix::Binary),
+            "oct" | "octal" => Some(Radix::Octal),
+            "dec" | "decimal" => Some(Radix::Decimal),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Radix::Hex => "hex",
+            Radix::Binary => "binary",
+            Radix::Octal => "octal",
+            Radix::Decimal => "decimal",
+        }
+    }
+}
+
+// What the result should be displayed as, given by the `to`/`in`/`as` suffix
+enum Target {
+    Radix(Radix),
+    Unit(&'static Unit),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Quantity {
+    value: f64,
+    unit: Option<&'static Unit>,
+}
+
+impl Quantity {
+    fn scalar(value: f64) -> Quantity {
+        Quantity { value, unit: None }
+    }
+
+    fn convert(self, target: &'static Unit) -> Result<Quantity, CalcError> {
+        match self.unit {
+            None => Ok(Quantity {
+                value: self.value,
+                unit: Some(target),
+            }),
+            Some(unit) if unit.dimension == target.dimension => Ok(Quantity {
+                value: target.from_base(unit.to_base(self.value)),
+                unit: Some(target),
+            }),
+            Some(unit) => Err(CalcError::IncompatibleUnits(
+                unit.symbol.to_string(),
+                target.symbol.to_string(),
+            )),
+        }
+    }
+}
+
+/// Evaluate an expression and format the result for display
+pub fn evaluate(input: &str) -> Result<String, CalcError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let quantity = parser.expression()?;
+    let target = parser.target()?;
+
+    if let Some(token) = parser.peek() {
+        return Err(CalcError::UnexpectedToken(format!("{token:?}")));
+    }
+
+    match target {
+        None => Ok(format_quantity(quantity)),
+        Some(Target::Unit(unit)) => quantity.convert(unit).map(format_quantity),
+        Some(Target::Radix(radix)) => format_radix(quantity.value, radix),
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, CalcError> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => tokens.push(Token::Number(number(&mut chars)?)),
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                chars.next();
+                tokens.push(Token::Op(c));
+            }
+            '×' => {
+                chars.next();
+                tokens.push(Token::Op('*'));
+            }
+            '÷' => {
+                chars.next();
+                tokens.push(Token::Op('/'));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LeftParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RightParen);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            c if c.is_alphabetic() || c == '°' || c == '_' => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '°' || c == '_' {
+                        ident.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            c => return Err(CalcError::UnexpectedChar(c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn number(chars: &mut Peekable<Chars>) -> Result<f64, CalcError> {
+    let mut literal = String::new();
+    while let Some(&c) = chars.peek() {
+        let radix = !is_decimal(&literal) && !literal.is_empty();
+        // Radix prefix, `0xff`
+        let radix_prefix =
+            literal == "0" && matches!(c, 'x' | 'X' | 'b' | 'B' | 'o' | 'O') && is_radix(chars);
+        // Exponent sign, `1e-3`
+        let exponent_sign =
+            (c == '+' || c == '-') && literal.ends_with(['e', 'E']) && is_decimal(&literal);
+
+        let accepted = c.is_ascii_digit()
+            || c == '.'
+            || c == '_'
+            || radix_prefix
+            || (radix && c.is_ascii_alphanumeric())
+            || ((c == 'e' || c == 'E') && is_exponent(chars))
+            || exponent_sign;
+
+        if !accepted {
+            break;
+        }
+
+        literal.push(c);
+        chars.next();
+    }
+
+    let literal = literal.replace('_', "");
+    let radix_literal = |prefix: &str, radix: u32| {
+        i64::from_str_radix(&literal[prefix.len()..], radix)
+            .map(|value| value as f64)
+            .map_err(|_| CalcError::UnexpectedToken(literal.clone()))
+    };
+
+    let lowercase = literal.to_lowercase();
+    if lowercase.starts_with("0x") {
+        radix_literal("0x", 16)
+    } else if lowercase.starts_with("0b") {
+        radix_literal("0b", 2)
+    } else if lowercase.starts_with("0o") {
+        radix_literal("0o", 8)
+    } else {
+        literal
+            .parse::<f64>()
+            .map_err(|_| CalcError::UnexpectedToken(literal.clone()))
+    }
+}
+
+// Letters after a decimal number start a glued unit, `10km`, unless they're an exponent, `1e-3`
+fn is_exponent(chars: &Peekable<Chars>) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next();
+    match ahead.next() {
+        Some('+' | '-') => ahead.next().is_some_and(|c| c.is_ascii_digit()),
+        Some(c) => c.is_ascii_digit(),
+        None => false,
+    }
+}
+
+// `0b1010` but not `0B`, zero bytes
+fn is_radix(chars: &Peekable<Chars>) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next();
+    ahead.next().is_some_and(|c| c.is_ascii_hexdigit())
+}
+
+fn is_decimal(literal: &str) -> bool {
+    !literal.to_lowercase().starts_with("0x")
+        && literal.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), CalcError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(CalcError::UnexpectedToken(format!("{token:?}"))),
+            None => Err(CalcError::UnexpectedEnd),
+        }
+    }
+
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<Quantity, CalcError> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.term()?;
+            // Both sides are expressed in the left hand side unit
+            let rhs = match lhs.unit {
+                Some(unit) => rhs.convert(unit)?,
+                None => rhs,
+            };
+
+            let value = if op == '+' {
+                lhs.value + rhs.value
+            } else {
+                lhs.value - rhs.value
+            };
+
+            lhs = Quantity {
+                value,
+                unit: lhs.unit.or(rhs.unit),
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    // term := power (('*' | '/' | '%') power)*
+    fn term(&mut self) -> Result<Quantity, CalcError> {
+        let mut lhs = self.power()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.power()?;
+            lhs = match (lhs.unit, rhs.unit) {
+                // A ratio of two quantities of the same dimension is a plain number
+                (Some(_), Some(unit)) if op == '/' => {
+                    let lhs = lhs.convert(unit)?;
+                    Quantity::scalar(lhs.value / rhs.value)
+                }
+                (Some(unit), Some(_)) => {
+                    return Err(CalcError::IncompatibleUnits(
+                        unit.symbol.to_string(),
+                        format!("{op}"),
+                    ))
+                }
+                (unit, other) => Quantity {
+                    value: match op {
+                        '*' => lhs.value * rhs.value,
+                        '/' => lhs.value / rhs.value,
+                        _ => lhs.value % rhs.value,
+                    },
+                    unit: unit.or(other),
+                },
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    // power := unary ('^' power)?
+    fn power(&mut self) -> Result<Quantity, CalcError> {
+        let base = self.unary()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.next();
+            let exponent = self.power()?;
+            return Ok(Quantity {
+                value: base.value.powf(exponent.value),
+                unit: base.unit,
+            });
+        }
+
+        Ok(base)
+    }
+
+    // unary := ('-' | '+') unary | postfix
+    fn unary(&mut self) -> Result<Quantity, CalcError> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.next();
+                let quantity = self.unary()?;
+                Ok(Quantity {
+                    value: -quantity.value,
+                    ..quantity
+                })
+            }
+            Some(Token::Op('+')) => {
+                self.next();
+                self.unary()
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    // postfix := primary (unit | constant)?
+    fn postfix(&mut self) -> Result<Quantity, CalcError> {
+        let quantity = self.primary()?;
+        if let Some(Token::Ident(name)) = self.peek().cloned() {
+            if self.is_unit_position(&name) {
+                if let Some(unit) = units::find(&name) {
+                    self.next();
+                    return quantity.convert(unit);
+                }
+            }
+
+            // Implicit multiplication, `2pi`
+            if self.peek_nth(1) != Some(&Token::LeftParen) {
+                if let Ok(value) = constant(&name) {
+                    self.next();
+                    return Ok(Quantity {
+                        value: quantity.value * value,
+                        ..quantity
+                    });
+                }
+            }
+        }
+
+        Ok(quantity)
+    }
+
+    // `in` is both a conversion keyword and the inch symbol:
+    // it's a unit when nothing but another conversion keyword follows it
+    fn is_unit_position(&self, name: &str) -> bool {
+        if name != "in" {
+            return !is_conversion_keyword(name);
+        }
+
+        match self.peek_nth(1) {
+            None => true,
+            Some(Token::Ident(next)) => is_conversion_keyword(next),
+            Some(_) => true,
+        }
+    }
+
+    // primary := number | constant | function '(' args ')' | '(' expression ')'
+    fn primary(&mut self) -> Result<Quantity, CalcError> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Quantity::scalar(value)),
+            Some(Token::LeftParen) => {
+                let quantity = self.expression()?;
+                self.expect(Token::RightParen)?;
+                Ok(quantity)
+            }
+            Some(Token::Ident(name)) => {
+                if let Some(Token::LeftParen) = self.peek() {
+                    self.next();
+                    let mut args = vec![self.expression()?];
+                    while let Some(Token::Comma) = self.peek() {
+                        self.next();
+                        args.push(self.expression()?);
+                    }
+                    self.expect(Token::RightParen)?;
+                    call(&name, &args)
+                } else {
+                    constant(&name).map(Quantity::scalar)
+                }
+            }
+            Some(token) => Err(CalcError::UnexpectedToken(format!("{token:?}"))),
+            None => Err(CalcError::UnexpectedEnd),
+        }
+    }
+
+    // target := ('to' | 'in' | 'as') (radix | unit)
+    fn target(&mut self) -> Result<Option<Target>, CalcError> {
+        match self.peek().cloned() {
+            Some(Token::Ident(keyword)) if is_conversion_keyword(&keyword) => {
+                self.next();
+                match self.next() {
+                    Some(Token::Ident(name)) => Radix::from_name(&name)
+                        .map(Target::Radix)
+                        .or_else(|| units::find(&name).map(Target::Unit))
+                        .map(Some)
+                        .ok_or(CalcError::Unknown(name)),
+                    Some(token) => Err(CalcError::UnexpectedToken(format!("{token:?}"))),
+                    None => Err(CalcError::UnexpectedEnd),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+fn is_conversion_keyword(name: &str) -> bool {
+    matches!(name, "to" | "in" | "as")
+}
+
+fn constant(name: &str) -> Result<f64, CalcError> {
+    match name.to_lowercase().as_str() {
+        "pi" | "π" => Ok(PI),
+        "e" => Ok(E),
+        "tau" | "τ" => Ok(2.0 * PI),
+        _ => Err(CalcError::Unknown(name.to_string())),
+    }
+}
+
+fn call(name: &str, args: &[Quantity]) -> Result<Quantity, CalcError> {
+    let x = args[0].value;
+    let unit = args[0].unit;
+    let value = match (name.to_lowercase().as_str(), args.len()) {
+        ("sqrt", 1) => x.sqrt(),
+        ("cbrt", 1) => x.cbrt(),
+        ("abs", 1) => {
+            return Ok(Quantity {
+                value: x.abs(),
+                unit,
+            })
+        }
+        ("floor", 1) => {
+            return Ok(Quantity {
+                value: x.floor(),
+                unit,
+            })
+        }
+        ("ceil", 1) => {
+            return Ok(Quantity {
+                value: x.ceil(),
+                unit,
+            })
+        }
+        ("round", 1) => {
+            return Ok(Quantity {
+                value: x.round(),
+                unit,
+            })
+        }
+        ("sin", 1) => x.sin(),
+        ("cos", 1) => x.cos(),
+        ("tan", 1) => x.tan(),
+        ("asin", 1) => x.asin(),
+        ("acos", 1) => x.acos(),
+        ("atan", 1) => x.atan(),
+        ("ln", 1) => x.ln(),
+        ("log", 1) | ("log10", 1) => x.log10(),
+        ("log2", 1) => x.log2(),
+        ("log", 2) => x.log(args[1].value),
+        ("exp", 1) => x.exp(),
+        ("pow", 2) => x.powf(args[1].value),
+        ("min", _) => args.iter().map(|q| q.value).fold(f64::INFINITY, f64::min),
+        ("max", _) => args
+            .iter()
+            .map(|q| q.value)
+            .fold(f64::NEG_INFINITY, f64::max),
+        _ => return Err(CalcError::Unknown(name.to_string())),
+    };
+
+    Ok(Quantity::scalar(value))
+}
+
+fn format_quantity(quantity: Quantity) -> String {
+    let value = format_number(quantity.value);
+    match quantity.unit {
+        Some(unit) => format!("{value} {}", unit.symbol),
+        None => value,
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+
+    let abs = value.abs();
+    if abs != 0.0 && !(1e-6..1e15).contains(&abs) {
+        return format!("{value:e}");
+    }
+
+    if value.fract() == 0.0 {
+        return format!("{}", value as i64);
+    }
+
+    let formatted = format!("{value:.10}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn format_radix(value: f64, radix: Radix) -> Result<String, CalcError> {
+    if value.fract() != 0.0 || !value.is_finite() {
+        return Err(CalcError::NotAnInteger(radix.name()));
+    }
+
+    let sign = if value < 0.0 { "-" } else { "" };
+    let abs = value.abs() as u64;
+    Ok(match radix {
+        Radix::Hex => format!("{sign}0x{abs:x}"),
+        Radix::Binary => format!("{sign}0b{abs:b}"),
+        Radix::Octal => format!("{sign}0o{abs:o}"),
+        Radix::Decimal => format!("{sign}{abs}"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::calc::{evaluate, CalcError};
+    use speculoos::prelude::*;
+
+    #[test]
+    fn should_respect_operator_precedence() {
+        asserting!("Should evaluate arithmetic")
+            .that(&evaluate("2 + 3 * 4 - 10 / 5"))
+            .is_ok()
+            .is_equal_to("12".to_string());
+    }
+
+    #[test]
+    fn should_evaluate_right_associative_power_and_unary_minus() {
+        asserting!("Should evaluate powers")
+            .that(&evaluate("-2^3^2"))
+            .is_ok()
+            .is_equal_to("-512".to_string());
+    }
+
+    #[test]
+    fn should_evaluate_functions_and_constants() {
+        asserting!("Should evaluate functions")
+            .that(&evaluate("sqrt(16) + round(pi * 100) / 100"))
+            .is_ok()
+            .is_equal_to("7.14".to_string());
+    }
+
+    #[test]
+    fn should_convert_to_hex_and_binary() {
+        assert_eq!(evaluate("255 to hex"), Ok("0xff".to_string()));
+        assert_eq!(evaluate("0xff + 1 in bin"), Ok("0b100000000".to_string()));
+        assert_eq!(evaluate("0b1010"), Ok("10".to_string()));
+    }
+
+    #[test]
+    fn should_refuse_radix_conversion_of_decimals() {
+        assert_eq!(evaluate("1.5 to hex"), Err(CalcError::NotAnInteger("hex")));
+    }
+
+    #[test]
+    fn should_convert_units() {
+        assert_eq!(evaluate("10 km to mi"), Ok("6.2137119224 mi".to_string()));
+        assert_eq!(evaluate("1 GiB in MiB"), Ok("1024 MiB".to_string()));
+        assert_eq!(evaluate("2 in to cm"), Ok("5.08 cm".to_string()));
+    }
+
+    #[test]
+    fn should_convert_temperatures() {
+        assert_eq!(evaluate("100 c to f"), Ok("212 °F".to_string()));
+        assert_eq!(evaluate("0 K in c"), Ok("-273.15 °C".to_string()));
+    }
+
+    #[test]
+    fn should_add_quantities_with_different_units() {
+        assert_eq!(evaluate("1 m + 50 cm"), Ok("1.5 m".to_string()));
+    }
+
+    #[test]
+    fn should_fail_on_incompatible_units() {
+        assert_eq!(
+            evaluate("1 kg to m"),
+            Err(CalcError::IncompatibleUnits(
+                "kg".to_string(),
+                "m".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn should_fail_on_incomplete_expression() {
+        assert_eq!(evaluate("1 +"), Err(CalcError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn should_evaluate_glued_units_and_constants() {
+        assert_eq!(evaluate("10km to mi"), Ok("6.2137119224 mi".to_string()));
+        assert_eq!(evaluate("1.5e3m to km"), Ok("1.5 km".to_string()));
+        assert_eq!(evaluate("2pi"), Ok("6.2831853072".to_string()));
+        assert_eq!(evaluate("1e-3 + 0xff"), Ok("255.001".to_string()));
+    }
+}
@@ -0,0 +1,136 @@
+// Units are converted through the base unit of their dimension:
+// `base = value * factor + offset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Area,
+    Volume,
+    Mass,
+    Time,
+    Speed,
+    Temperature,
+    Data,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Unit {
+    pub symbol: &'static str,
+    pub aliases: &'static [&'static str],
+    pub dimension: Dimension,
+    pub factor: f64,
+    pub offset: f64,
+}
+
+impl Unit {
+    const fn new(
+        symbol: &'static str,
+        aliases: &'static [&'static str],
+        dimension: Dimension,
+        factor: f64,
+    ) -> Unit {
+        Unit {
+            symbol,
+            aliases,
+            dimension,
+            factor,
+            offset: 0.0,
+        }
+    }
+
+    pub fn to_base(&self, value: f64) -> f64 {
+        value * self.factor + self.offset
+    }
+
+    pub fn from_base(&self, value: f64) -> f64 {
+        (value - self.offset) / self.factor
+    }
+}
+
+use Dimension::*;
+
+pub static UNITS: &[Unit] = &[
+    // Length, base: meter
+    Unit::new("mm", &["millimeter", "millimeters"], Length, 0.001),
+    Unit::new("cm", &["centimeter", "centimeters"], Length, 0.01),
+    Unit::new("m", &["meter", "meters", "metre", "metres"], Length, 1.0),
+    Unit::new("km", &["kilometer", "kilometers"], Length, 1000.0),
+    Unit::new("in", &["inch", "inches"], Length, 0.0254),
+    Unit::new("ft", &["foot", "feet"], Length, 0.3048),
+    Unit::new("yd", &["yard", "yards"], Length, 0.9144),
+    Unit::new("mi", &["mile", "miles"], Length, 1609.344),
+    Unit::new("nmi", &["nauticalmile"], Length, 1852.0),
+    // Area, base: square meter
+    Unit::new("m2", &["sqm"], Area, 1.0),
+    Unit::new("km2", &["sqkm"], Area, 1_000_000.0),
+    Unit::new("ha", &["hectare", "hectares"], Area, 10_000.0),
+    Unit::new("acre", &["acres"], Area, 4_046.856_422_4),
+    Unit::new("ft2", &["sqft"], Area, 0.092_903_04),
+    // Volume, base: liter
+    Unit::new("ml", &["milliliter", "milliliters"], Volume, 0.001),
+    Unit::new("cl", &["centiliter", "centiliters"], Volume, 0.01),
+    Unit::new("l", &["liter", "liters", "litre", "litres"], Volume, 1.0),
+    Unit::new("m3", &[], Volume, 1000.0),
+    Unit::new("gal", &["gallon", "gallons"], Volume, 3.785_411_784),
+    Unit::new("floz", &[], Volume, 0.029_573_529_562_5),
+    Unit::new("cup", &["cups"], Volume, 0.236_588_236_5),
+    // Mass, base: kilogram
+    Unit::new("mg", &["milligram", "milligrams"], Mass, 0.000_001),
+    Unit::new("g", &["gram", "grams"], Mass, 0.001),
+    Unit::new("kg", &["kilogram", "kilograms"], Mass, 1.0),
+    Unit::new("t", &["tonne", "tonnes"], Mass, 1000.0),
+    Unit::new("oz", &["ounce", "ounces"], Mass, 0.028_349_523_125),
+    Unit::new("lb", &["lbs", "pound", "pounds"], Mass, 0.453_592_37),
+    Unit::new("st", &["stone"], Mass, 6.350_293_18),
+    // Time, base: second
+    Unit::new("ms", &["millisecond", "milliseconds"], Time, 0.001),
+    Unit::new("s", &["sec", "second", "seconds"], Time, 1.0),
+    Unit::new("min", &["minute", "minutes"], Time, 60.0),
+    Unit::new("h", &["hour", "hours"], Time, 3600.0),
+    Unit::new("day", &["days"], Time, 86_400.0),
+    Unit::new("week", &["weeks"], Time, 604_800.0),
+    Unit::new("year", &["years"], Time, 31_557_600.0),
+    // Speed, base: meter per second
+    Unit::new("mps", &[], Speed, 1.0),
+    Unit::new("kph", &["kmh"], Speed, 1000.0 / 3600.0),
+    Unit::new("mph", &[], Speed, 0.447_04),
+    Unit::new("knot", &["knots", "kn"], Speed, 1852.0 / 3600.0),
+    // Temperature, base: kelvin
+    Unit {
+        symbol: "°C",
+        aliases: &["c", "celsius"],
+        dimension: Temperature,
+        factor: 1.0,
+        offset: 273.15,
+    },
+    Unit {
+        symbol: "°F",
+        aliases: &["f", "fahrenheit"],
+        dimension: Temperature,
+        factor: 5.0 / 9.0,
+        offset: 459.67 * 5.0 / 9.0,
+    },
+    Unit::new("K", &["k", "kelvin"], Temperature, 1.0),
+    // Data, base: byte
+    Unit::new("bit", &["bits"], Data, 0.125),
+    Unit::new("B", &["byte", "bytes"], Data, 1.0),
+    Unit::new("kB", &["kb"], Data, 1e3),
+    Unit::new("MB", &["mb"], Data, 1e6),
+    Unit::new("GB", &["gb"], Data, 1e9),
+    Unit::new("TB", &["tb"], Data, 1e12),
+    Unit::new("KiB", &["kib"], Data, 1024.0),
+    Unit::new("MiB", &["mib"], Data, 1_048_576.0),
+    Unit::new("GiB", &["gib"], Data, 1_073_741_824.0),
+    Unit::new("TiB", &["tib"], Data, 1_099_511_627_776.0),
+];
+
+// Symbols are case sensitive (`MB` vs `mb` is not ambiguous here but `K` and `k` are
+// both kelvin), aliases are matched ignoring case
+pub fn find(name: &str) -> Option<&'static Unit> {
+    UNITS.iter().find(|unit| unit.symbol == name).or_else(|| {
+        let lowercase = name.to_lowercase();
+        UNITS.iter().find(|unit| {
+            unit.symbol.to_lowercase() == lowercase
+                || unit.aliases.iter().any(|alias| *alias == lowercase)
+        })
+    })
+}
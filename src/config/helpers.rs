@@ -42,6 +42,13 @@ pub fn unwrap_attr_str(pair: Pair<'_, Rule>) -> &str {
     pair.into_inner().last().unwrap().into_inner().as_str()
 }
 
+// A comma separated list of strings, e.g: "Papirus", "Adwaita"
+pub fn unwrap_attr_str_list(pair: Pair<'_, Rule>) -> Vec<String> {
+    pair.into_inner()
+        .map(|string| string.into_inner().as_str().to_string())
+        .collect()
+}
+
 pub fn unwrap_attr_bool(pair: Pair<'_, Rule>) -> bool {
     let value = pair.into_inner().last().unwrap().as_str();
 
@@ -103,8 +110,8 @@ pub fn unwrap_length(pair: Pair<'_, Rule>) -> Result<Length, ConfigError> {
 mod test {
     use crate::config::color::OnagreColor;
     use crate::config::helpers::{
-        unwrap_attr_bool, unwrap_attr_f32, unwrap_attr_str, unwrap_attr_u16, unwrap_hex_color,
-        unwrap_length, unwrap_x, unwrap_y,
+        unwrap_attr_bool, unwrap_attr_f32, unwrap_attr_str, unwrap_attr_str_list, unwrap_attr_u16,
+        unwrap_hex_color, unwrap_length, unwrap_x, unwrap_y,
     };
     use crate::config::{Rule, ThemeParser};
     use iced::alignment::{Horizontal, Vertical};
@@ -464,11 +471,28 @@ mod test {
             .next()
             .unwrap();
 
-        let icon_theme = unwrap_attr_str(pair);
+        let icon_theme = unwrap_attr_str_list(pair);
 
         asserting!("Should parse 'icon_theme' attribute")
             .that(&icon_theme)
-            .is_equal_to("Arc");
+            .is_equal_to(vec!["Arc".to_string()]);
+    }
+
+    #[test]
+    fn should_parse_icon_theme_list() {
+        let pair = ThemeParser::parse(
+            Rule::icon_theme,
+            r#"--icon-theme: "Papirus-Dark", "Adwaita";"#,
+        )
+        .unwrap()
+        .next()
+        .unwrap();
+
+        let icon_theme = unwrap_attr_str_list(pair);
+
+        asserting!("Should parse every theme in order")
+            .that(&icon_theme)
+            .is_equal_to(vec!["Papirus-Dark".to_string(), "Adwaita".to_string()]);
     }
 
     #[test]
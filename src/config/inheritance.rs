@@ -43,6 +43,10 @@ impl Inherit for Theme {
         self.app_container.rows.row.category_icon.icon_size = self.icon_size;
         self.app_container.rows.row_selected.icon.icon_size = self.icon_size;
         self.app_container.rows.row_selected.category_icon.icon_size = self.icon_size;
+        self.app_container.rows.row_marked.icon.icon_size = self.icon_size;
+        self.app_container.rows.row_marked.category_icon.icon_size = self.icon_size;
+        self.app_container.rows.row_pinned.icon.icon_size = self.icon_size;
+        self.app_container.rows.row_pinned.category_icon.icon_size = self.icon_size;
     }
 
     fn propagate_color(&mut self) {
@@ -78,6 +82,12 @@ impl Inherit for RowContainerStyle {
 
         self.row_selected.background = self.background;
         self.row_selected.propagate_background();
+
+        self.row_marked.background = self.background;
+        self.row_marked.propagate_background();
+
+        self.row_pinned.background = self.background;
+        self.row_pinned.propagate_background();
     }
 
     fn propagate_color(&mut self) {
@@ -86,6 +96,12 @@ impl Inherit for RowContainerStyle {
 
         self.row_selected.color = self.color;
         self.row_selected.propagate_color();
+
+        self.row_marked.color = self.color;
+        self.row_marked.propagate_color();
+
+        self.row_pinned.color = self.color;
+        self.row_pinned.propagate_color();
     }
 }
 
@@ -64,9 +64,7 @@ impl TryFrom<Pair<'_, Rule>> for Theme {
                     theme.font_size = helpers::unwrap_attr_u16(pair)?;
                     theme.propagate_font_size();
                 }
-                Rule::icon_theme => {
-                    theme.icon_theme = Some(helpers::unwrap_attr_str(pair).to_string())
-                }
+                Rule::icon_theme => theme.icon_theme = Some(helpers::unwrap_attr_str_list(pair)),
                 Rule::icon_size => {
                     theme.icon_size = helpers::unwrap_attr_u16(pair)?;
                     theme.propagate_icon_size();
@@ -264,6 +262,7 @@ impl ApplyConfig for SearchInputStyles {
 
 impl ApplyConfig for RowContainerStyle {
     fn apply(&mut self, pair: Pair<'_, Rule>) -> Result<(), ConfigError> {
+        let mut pinned_row_styled = false;
         for pair in pair.into_inner() {
             match pair.as_rule() {
                 // Style
@@ -293,10 +292,20 @@ impl ApplyConfig for RowContainerStyle {
                 // Children
                 Rule::default_row => self.row.apply(pair)?,
                 Rule::selected_row => self.row_selected.apply(pair)?,
+                Rule::marked_row => self.row_marked.apply(pair)?,
+                Rule::pinned_row => {
+                    pinned_row_styled = true;
+                    self.row_pinned.apply(pair)?
+                }
                 _ => unreachable!(),
             }
         }
 
+        // Pinned rows look like any other row unless styled
+        if !pinned_row_styled {
+            self.row_pinned = self.row.clone();
+        }
+
         Ok(())
     }
 }
@@ -431,6 +440,16 @@ impl Theme {
                         hide_category_icon: true,
                         ..Default::default()
                     },
+                    row_marked: RowStyles {
+                        hide_description: true,
+                        hide_category_icon: true,
+                        ..RowStyles::default_marked()
+                    },
+                    row_pinned: RowStyles {
+                        hide_description: true,
+                        hide_category_icon: true,
+                        ..Default::default()
+                    },
                     ..Default::default()
                 },
                 ..Default::default()
@@ -0,0 +1,37 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::{Database, Entity};
+use crate::settings::AliasTarget;
+
+pub const COLLECTION: &str = "aliases";
+
+// An alias added from the UI, aliases from the config take precedence
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AliasEntity<'a> {
+    pub keyword: Cow<'a, str>,
+    pub target: AliasTarget,
+}
+
+impl<'a> Entity<'a> for AliasEntity<'a> {
+    fn get_key(&self) -> Cow<'a, str> {
+        self.keyword.clone()
+    }
+
+    fn get_weight(&self) -> u8 {
+        0
+    }
+}
+
+impl AliasEntity<'_> {
+    pub fn persist(keyword: &str, target: AliasTarget, db: &Database) {
+        let entity = AliasEntity {
+            keyword: Cow::Borrowed(keyword),
+            target,
+        };
+
+        db.insert(COLLECTION, &entity)
+            .expect("Unable to insert alias");
+    }
+}
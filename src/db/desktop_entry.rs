@@ -2,46 +2,321 @@ use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
+use tracing::{error, info};
 
-use crate::db::{Database, Entity};
-use crate::freedesktop::desktop::DesktopEntry;
+use crate::db::{timestamp, Database, Entity};
+use crate::freedesktop::desktop::{
+    desktop_file_id, find_desktop_file, DesktopEntry, DesktopEntryOrigin,
+};
 
 pub const COLLECTION: &str = "desktop-entries";
 
+// Launch times kept for each entry, used to rank the history by time of day
+pub(crate) const MAX_RECORDED_LAUNCHES: usize = 50;
+
+// Entries whose desktop file stays missing for this long are removed from the history,
+// the file might only be gone during a package upgrade or while a drive is unmounted
+const STALE_GRACE_PERIOD: u64 = 30 * 24 * 60 * 60;
+
+// A launched desktop entry, keyed by desktop file id
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DesktopEntryEntity<'a> {
+    // Only used for display, the same application can be installed several times
     pub name: Cow<'a, str>,
     pub icon: Option<Cow<'a, str>>,
     pub description: Option<Cow<'a, str>>,
     pub path: PathBuf,
     pub weight: u8,
+    // Seconds since the unix epoch
+    #[serde(default)]
+    pub last_used: u64,
+    // When the desktop file was first found missing, in seconds since the unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missing_since: Option<u64>,
+    // The most recent launch times, in seconds since the unix epoch, see `db::ranking`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub launches: Vec<u64>,
 }
 
 impl<'a> Entity<'a> for DesktopEntryEntity<'a> {
     fn get_key(&self) -> Cow<'a, str> {
-        self.name.clone()
+        match self.desktop_file_id() {
+            Some(id) => Cow::Owned(id),
+            None => self.name.clone(),
+        }
     }
     fn get_weight(&self) -> u8 {
         self.weight
     }
+
+    fn get_last_used(&self) -> u64 {
+        self.last_used
+    }
 }
 
 impl<'a> DesktopEntryEntity<'a> {
     pub fn persist(entry: &'a DesktopEntry, path: &Path, db: &Database) {
-        let weight = match db.get_by_key::<DesktopEntryEntity>(COLLECTION, &entry.name) {
-            Some(de_entry) => de_entry.weight + 1,
-            None => 0,
-        };
-
-        let entity = Self {
+        let now = timestamp();
+        let mut entity = Self {
             name: Cow::Borrowed(entry.name.as_ref()),
             icon: entry.icon.as_deref().map(Cow::Borrowed),
             description: entry.comment.as_ref().cloned(),
             path: path.into(),
-            weight,
+            weight: 0,
+            last_used: now,
+            missing_since: None,
+            launches: vec![],
         };
 
-        db.insert(COLLECTION, &entity)
+        if let Some(de_entry) = db.get_by_key::<DesktopEntryEntity>(COLLECTION, &entity.get_key()) {
+            entity.weight = de_entry.weight.saturating_add(1);
+            entity.launches = de_entry.launches;
+        }
+
+        entity.launches.push(now);
+        let excess = entity.launches.len().saturating_sub(MAX_RECORDED_LAUNCHES);
+        entity.launches.drain(..excess);
+
+        db.record(COLLECTION, &entity)
             .expect("Unable to insert history entry");
     }
+
+    // Build a history entry for a desktop file that was never launched
+    pub fn from_desktop_file(id: &str) -> Option<DesktopEntryEntity<'static>> {
+        let path = find_desktop_file(id)?;
+        let entry = DesktopEntry::from_path(&path)?;
+        Some(DesktopEntryEntity {
+            name: Cow::Owned(entry.name.into_owned()),
+            icon: entry.icon.map(|icon| Cow::Owned(icon.into_owned())),
+            description: entry
+                .comment
+                .map(|comment| Cow::Owned(comment.into_owned())),
+            path,
+            weight: 0,
+            last_used: 0,
+            missing_since: None,
+            launches: vec![],
+        })
+    }
+
+    pub fn desktop_file_id(&self) -> Option<String> {
+        desktop_file_id(&self.path)
+    }
+
+    pub fn origin(&self) -> DesktopEntryOrigin {
+        DesktopEntryOrigin::from_path(&self.path)
+    }
+}
+
+// Hide entries whose desktop file disappeared. Moved desktop files are found again with `resolve`,
+// entries missing for longer than the grace period are removed from the database.
+pub fn remove_stale<'a, F>(
+    entries: Vec<DesktopEntryEntity<'a>>,
+    db: &Database,
+    now: u64,
+    resolve: F,
+) -> Vec<DesktopEntryEntity<'a>>
+where
+    F: Fn(&str) -> Option<PathBuf>,
+{
+    entries
+        .into_iter()
+        .filter_map(|mut entry| {
+            if entry.path.is_file() {
+                if entry.missing_since.take().is_some() {
+                    update(db, &entry);
+                }
+                return Some(entry);
+            }
+
+            if let Some(path) = entry.desktop_file_id().and_then(|id| resolve(&id)) {
+                info!("Desktop file {:?} moved to {path:?}", entry.path);
+                entry.path = path;
+                entry.missing_since = None;
+                update(db, &entry);
+                return Some(entry);
+            }
+
+            match entry.missing_since {
+                Some(since) if now.saturating_sub(since) > STALE_GRACE_PERIOD => {
+                    info!("Removing stale history entry {:?}", entry.path);
+                    if !db.is_incognito() {
+                        let key = entry.get_key();
+                        if let Err(err) = db.remove(COLLECTION, &key) {
+                            error!("Failed to remove stale history entry '{key}': {err}");
+                        }
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    info!("Desktop file {:?} is missing, hiding it", entry.path);
+                    entry.missing_since = Some(now);
+                    update(db, &entry);
+                }
+            }
+
+            None
+        })
+        .collect()
+}
+
+// Stale entries are not history, but incognito sessions still leave the database untouched
+fn update(db: &Database, entry: &DesktopEntryEntity) {
+    if db.is_incognito() {
+        return;
+    }
+
+    if let Err(err) = db.insert(COLLECTION, entry) {
+        error!("Failed to update history entry {:?}: {err}", entry.path);
+    }
+}
+
+// Move pinned entries first, in pinning order. Pinned ids missing from the history
+// are built with `resolve`.
+pub fn pin_first<'a, F>(
+    entries: Vec<DesktopEntryEntity<'a>>,
+    pinned: &[String],
+    resolve: F,
+) -> Vec<DesktopEntryEntity<'a>>
+where
+    F: Fn(&str) -> Option<DesktopEntryEntity<'a>>,
+{
+    let (mut pinned_entries, others): (Vec<_>, Vec<_>) =
+        entries
+            .into_iter()
+            .partition(|entry| match entry.desktop_file_id() {
+                Some(id) => pinned.contains(&id),
+                None => false,
+            });
+
+    let position = |entry: &DesktopEntryEntity| {
+        entry
+            .desktop_file_id()
+            .and_then(|id| pinned.iter().position(|pinned| *pinned == id))
+    };
+
+    for id in pinned {
+        if !pinned_entries
+            .iter()
+            .any(|entry| entry.desktop_file_id().as_ref() == Some(id))
+        {
+            pinned_entries.extend(resolve(id));
+        }
+    }
+
+    pinned_entries.sort_by_key(position);
+    pinned_entries.extend(others);
+    pinned_entries
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::db::desktop_entry::{
+        pin_first, remove_stale, DesktopEntryEntity, COLLECTION, STALE_GRACE_PERIOD,
+    };
+    use crate::db::migration::test::temp_path;
+    use crate::db::Database;
+    use speculoos::prelude::*;
+    use std::borrow::Cow;
+    use std::path::PathBuf;
+
+    pub(crate) fn entity(id: &str, weight: u8) -> DesktopEntryEntity<'static> {
+        DesktopEntryEntity {
+            name: Cow::Owned(id.to_string()),
+            icon: None,
+            description: None,
+            path: PathBuf::from("/usr/share/applications").join(id),
+            weight,
+            last_used: 0,
+            missing_since: None,
+            launches: vec![],
+        }
+    }
+
+    #[test]
+    fn should_put_pinned_entries_first_in_pinning_order() {
+        let entries = vec![
+            entity("firefox.desktop", 10),
+            entity("foot.desktop", 5),
+            entity("gimp.desktop", 1),
+        ];
+        let pinned = vec!["gimp.desktop".to_string(), "foot.desktop".to_string()];
+
+        let entries = pin_first(entries, &pinned, |_| None);
+
+        let names: Vec<_> = entries.iter().map(|entry| entry.name.as_ref()).collect();
+        assert_eq!(
+            names,
+            vec!["gimp.desktop", "foot.desktop", "firefox.desktop"]
+        );
+    }
+
+    #[test]
+    fn should_resolve_pinned_entries_missing_from_history() {
+        let entries = vec![entity("firefox.desktop", 10)];
+        let pinned = vec!["foot.desktop".to_string()];
+
+        let entries = pin_first(entries, &pinned, |id| Some(entity(id, 0)));
+
+        let names: Vec<_> = entries.iter().map(|entry| entry.name.as_ref()).collect();
+        assert_eq!(names, vec!["foot.desktop", "firefox.desktop"]);
+    }
+
+    fn stored(db: &Database, id: &str) -> Option<DesktopEntryEntity<'static>> {
+        db.get_by_key::<DesktopEntryEntity>(COLLECTION, id)
+    }
+
+    #[test]
+    fn should_hide_then_remove_missing_entries() {
+        let db = Database::in_memory().unwrap();
+        let missing = DesktopEntryEntity {
+            path: PathBuf::from("/nonexistent/applications/uninstalled.desktop"),
+            ..entity("uninstalled.desktop", 3)
+        };
+        db.insert(COLLECTION, &missing).unwrap();
+
+        let now = 1_000;
+        let entries = remove_stale(vec![missing], &db, now, |_| None);
+        asserting!("Should hide missing entries")
+            .that(&entries)
+            .is_empty();
+
+        let missing = stored(&db, "uninstalled.desktop").unwrap();
+        assert_eq!(missing.missing_since, Some(now));
+
+        remove_stale(vec![missing.clone()], &db, now + STALE_GRACE_PERIOD, |_| {
+            None
+        });
+        asserting!("Should keep missing entries during the grace period")
+            .that(&stored(&db, "uninstalled.desktop"))
+            .is_some();
+
+        remove_stale(vec![missing], &db, now + STALE_GRACE_PERIOD + 1, |_| None);
+        asserting!("Should remove entries missing after the grace period")
+            .that(&stored(&db, "uninstalled.desktop"))
+            .is_none();
+    }
+
+    #[test]
+    fn should_resolve_moved_entries() {
+        let db = Database::in_memory().unwrap();
+        let moved_to = temp_path("applications-moved.desktop");
+        std::fs::write(&moved_to, "").unwrap();
+        let moved = DesktopEntryEntity {
+            path: PathBuf::from("/nonexistent/applications/moved.desktop"),
+            missing_since: Some(1),
+            ..entity("moved.desktop", 3)
+        };
+
+        let entries = remove_stale(vec![moved], &db, 1_000, |id| {
+            assert_eq!(id, "moved.desktop");
+            Some(moved_to.clone())
+        });
+
+        let paths: Vec<_> = entries.iter().map(|entry| entry.path.clone()).collect();
+        assert_eq!(paths, std::slice::from_ref(&moved_to));
+        assert_eq!(entries[0].missing_since, None);
+
+        std::fs::remove_file(moved_to).unwrap();
+    }
 }
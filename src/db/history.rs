@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use anyhow::bail;
+use redb::backends::InMemoryBackend;
+use redb::{ReadableTable, TableDefinition, TableHandle};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::{debug, warn};
+
+use crate::db::desktop_entry::MAX_RECORDED_LAUNCHES;
+use crate::db::migration::{self, SCHEMA_VERSION};
+use crate::db::Database;
+use crate::settings::HistoryConfig;
+
+// Rows of each collection keyed by entity key, as stored in the database
+pub type Collections = BTreeMap<String, BTreeMap<String, Map<String, Value>>>;
+
+// A portable dump of onagre history
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryExport {
+    pub schema_version: u64,
+    pub collections: Collections,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub inserted: usize,
+    pub merged: usize,
+}
+
+// Write the history as json, every collection is exported unless some are requested
+pub fn export<W: Write>(db: &Database, collections: &[String], writer: W) -> anyhow::Result<()> {
+    let export = db.export(collections)?;
+    serde_json::to_writer_pretty(writer, &export)?;
+    Ok(())
+}
+
+pub fn import<R: Read>(db: &Database, reader: R) -> anyhow::Result<ImportSummary> {
+    let export: HistoryExport = serde_json::from_reader(reader)?;
+    db.import(&export)
+}
+
+impl Database {
+    pub fn export(&self, collections: &[String]) -> anyhow::Result<HistoryExport> {
+        let read_txn = self.inner.begin_read()?;
+        let mut exported = Collections::new();
+        let names: Vec<String> = read_txn
+            .list_tables()?
+            .map(|table| table.name().to_string())
+            .filter(|name| name != migration::METADATA.name())
+            .filter(|name| collections.is_empty() || collections.contains(name))
+            .collect();
+
+        for name in names {
+            let table = read_txn.open_table(TableDefinition::<&str, &str>::new(&name))?;
+            let mut rows = BTreeMap::new();
+            for row in table.iter()? {
+                let (key, value) = row?;
+                match serde_json::from_str(value.value()) {
+                    Ok(entity) => {
+                        rows.insert(key.value().to_string(), entity);
+                    }
+                    Err(err) => warn!("Skipping undecodable row '{}': {err}", key.value()),
+                }
+            }
+
+            exported.insert(name, rows);
+        }
+
+        for missing in collections
+            .iter()
+            .filter(|name| !exported.contains_key(*name))
+        {
+            warn!("Collection '{missing}' does not exist");
+        }
+
+        Ok(HistoryExport {
+            schema_version: migration::schema_version(&self.inner)?,
+            collections: exported,
+        })
+    }
+
+    // Merge an export into the database, launch counts are added to the existing ones
+    pub fn import(&self, export: &HistoryExport) -> anyhow::Result<ImportSummary> {
+        if export.schema_version > SCHEMA_VERSION {
+            bail!(
+                "History was exported with schema version {}, this version of onagre supports up to {SCHEMA_VERSION}",
+                export.schema_version
+            );
+        }
+
+        // Older rows would not match the current keys, e.g: desktop entries keyed by name
+        if export.schema_version < SCHEMA_VERSION {
+            return self.import(&upgrade(export)?);
+        }
+
+        let mut summary = ImportSummary::default();
+        let write_txn = self.inner.begin_write()?;
+        let collections = export
+            .collections
+            .iter()
+            .filter(|(collection, _)| collection.as_str() != migration::METADATA.name());
+
+        for (collection, rows) in collections {
+            let mut table = write_txn.open_table(TableDefinition::<&str, &str>::new(collection))?;
+            for (key, row) in rows {
+                let existing = table.get(key.as_str())?.and_then(|value| {
+                    serde_json::from_str::<Map<String, Value>>(value.value()).ok()
+                });
+
+                let row = match existing {
+                    Some(existing) => {
+                        summary.merged += 1;
+                        merge_rows(existing, row)
+                    }
+                    None => {
+                        summary.inserted += 1;
+                        row.clone()
+                    }
+                };
+
+                debug!("Importing '{key}' into '{collection}'");
+                table.insert(key.as_str(), serde_json::to_string(&row)?.as_str())?;
+            }
+        }
+
+        write_txn.commit()?;
+        Ok(summary)
+    }
+}
+
+// Run the pending migrations on the rows of an older export, in a scratch database
+fn upgrade(export: &HistoryExport) -> anyhow::Result<HistoryExport> {
+    debug!(
+        "Upgrading history from schema version {} to {SCHEMA_VERSION}",
+        export.schema_version
+    );
+
+    let scratch = redb::Database::builder().create_with_backend(InMemoryBackend::new())?;
+    let write_txn = scratch.begin_write()?;
+    let collections = export
+        .collections
+        .iter()
+        .filter(|(collection, _)| collection.as_str() != migration::METADATA.name());
+
+    for (collection, rows) in collections {
+        let mut table = write_txn.open_table(TableDefinition::<&str, &str>::new(collection))?;
+        for (key, row) in rows {
+            table.insert(key.as_str(), serde_json::to_string(row)?.as_str())?;
+        }
+    }
+
+    migration::set_schema_version(&write_txn, export.schema_version)?;
+    write_txn.commit()?;
+    migration::migrate(&scratch)?;
+
+    let scratch = Database {
+        inner: Arc::new(scratch),
+        incognito: true,
+        history: HistoryConfig::default(),
+    };
+
+    scratch.export(&[])
+}
+
+// Launch counts are summed, the most recent usage and launch times are kept,
+// other fields are left untouched
+pub(crate) fn merge_rows(
+    mut existing: Map<String, Value>,
+    imported: &Map<String, Value>,
+) -> Map<String, Value> {
+    let field = |row: &Map<String, Value>, name: &str| row.get(name).and_then(Value::as_u64);
+
+    if let (Some(weight), Some(imported_weight)) =
+        (field(&existing, "weight"), field(imported, "weight"))
+    {
+        // Weights are stored as u8
+        let weight = weight.saturating_add(imported_weight).min(u8::MAX as u64);
+        existing.insert("weight".to_string(), Value::from(weight));
+    }
+
+    if let Some(imported_last_used) = field(imported, "last_used") {
+        let last_used = field(&existing, "last_used")
+            .unwrap_or(0)
+            .max(imported_last_used);
+        existing.insert("last_used".to_string(), Value::from(last_used));
+    }
+
+    if let Some(Value::Array(imported_launches)) = imported.get("launches") {
+        let mut launches: Vec<u64> = existing
+            .get("launches")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .chain(imported_launches)
+            .filter_map(Value::as_u64)
+            .collect();
+
+        launches.sort_unstable();
+        launches.dedup();
+        let excess = launches.len().saturating_sub(MAX_RECORDED_LAUNCHES);
+        launches.drain(..excess);
+        existing.insert("launches".to_string(), Value::from(launches));
+    }
+
+    existing
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::desktop_entry::{self, DesktopEntryEntity};
+    use crate::db::history::{export, import, ImportSummary};
+    use crate::db::migration::test::{temp_path, v0_fixture};
+    use crate::db::query::{self, QueryEntity};
+    use crate::db::web::WebEntity;
+    use crate::db::Database;
+    use speculoos::prelude::*;
+    use std::borrow::Cow;
+    use std::path::PathBuf;
+
+    #[test]
+    fn should_export_and_merge_history() {
+        let source_path = v0_fixture("export");
+        let source = Database::open(&source_path).unwrap();
+        let mut json = vec![];
+        export(&source, &[], &mut json).unwrap();
+
+        let target_path = temp_path("import");
+        let target = Database::open(&target_path).unwrap();
+
+        asserting!("Should insert every row")
+            .that(&import(&target, json.as_slice()).unwrap())
+            .is_equal_to(ImportSummary {
+                inserted: 5,
+                merged: 0,
+            });
+
+        asserting!("Should merge existing rows")
+            .that(&import(&target, json.as_slice()).unwrap())
+            .is_equal_to(ImportSummary {
+                inserted: 0,
+                merged: 5,
+            });
+
+        let firefox =
+            target.get_by_key::<DesktopEntryEntity>(desktop_entry::COLLECTION, "firefox.desktop");
+        assert_eq!(firefox.map(|entry| entry.weight), Some(6));
+
+        let web = target.get_by_key::<WebEntity>("ddg", "rust redb");
+        assert_eq!(web.map(|web| web.weight), Some(4));
+
+        let queries = target.get_all::<QueryEntity>(query::COLLECTION);
+        assert_eq!(queries.len(), 1);
+
+        std::fs::remove_file(source_path).unwrap();
+        std::fs::remove_file(target_path).unwrap();
+    }
+
+    #[test]
+    fn should_export_requested_collections() {
+        let path = v0_fixture("export-collection");
+        let db = Database::open(&path).unwrap();
+
+        let export = db.export(&["terminal".to_string()]).unwrap();
+        let collections: Vec<_> = export.collections.keys().collect();
+
+        assert_eq!(collections, ["terminal"]);
+        assert!(export.collections["terminal"].contains_key("htop"));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_migrate_older_export() {
+        let path = temp_path("import-older");
+        let db = Database::open(&path).unwrap();
+        db.insert(
+            desktop_entry::COLLECTION,
+            &DesktopEntryEntity {
+                name: Cow::Borrowed("Firefox"),
+                icon: None,
+                description: None,
+                path: PathBuf::from("/usr/share/applications/firefox.desktop"),
+                weight: 2,
+                last_used: 0,
+                missing_since: None,
+                launches: vec![],
+            },
+        )
+        .unwrap();
+
+        let json = r#"{
+            "schema_version": 1,
+            "collections": {
+                "desktop-entries": {
+                    "Firefox": {
+                        "name": "Firefox",
+                        "path": "/usr/share/applications/firefox.desktop",
+                        "weight": 3
+                    }
+                }
+            }
+        }"#;
+
+        asserting!("Should merge name keyed rows into the existing entry")
+            .that(&import(&db, json.as_bytes()).unwrap())
+            .is_equal_to(ImportSummary {
+                inserted: 0,
+                merged: 1,
+            });
+
+        let entries = db.get_all::<DesktopEntryEntity>(desktop_entry::COLLECTION);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].weight, 5);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_reject_newer_export() {
+        let path = temp_path("import-newer");
+        let db = Database::open(&path).unwrap();
+        let json = r#"{ "schema_version": 999, "collections": {} }"#;
+
+        asserting!("Should not import rows from a newer schema")
+            .that(&import(&db, json.as_bytes()))
+            .is_err();
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
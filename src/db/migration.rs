@@ -0,0 +1,401 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use redb::{ReadableTable, TableDefinition, TableError, TableHandle, WriteTransaction};
+use serde_json::{Map, Value};
+use tracing::{debug, info, warn};
+
+use crate::db::desktop_entry;
+use crate::db::history::merge_rows;
+use crate::freedesktop::desktop::desktop_file_id;
+
+// Database metadata, kept apart from the entity collections
+pub(crate) const METADATA: TableDefinition<&str, u64> = TableDefinition::new("onagre-metadata");
+const SCHEMA_VERSION_KEY: &str = "schema-version";
+
+// The schema version written by this version of onagre,
+// databases created before schema versioning are at version 0
+pub const SCHEMA_VERSION: u64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+// Upgrades every row from `version - 1` to `version`
+pub(crate) struct Migration {
+    pub version: u64,
+    pub description: &'static str,
+    pub migrate: fn(&WriteTransaction) -> Result<(), redb::Error>,
+}
+
+// Ordered by version, never edit a released migration, add a new one instead
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "remove rows that are not json objects",
+        migrate: remove_undecodable_rows,
+    },
+    Migration {
+        version: 2,
+        description: "key desktop entries by desktop file id",
+        migrate: key_desktop_entries_by_id,
+    },
+];
+
+pub fn schema_version(db: &redb::Database) -> Result<u64, redb::Error> {
+    let read_txn = db.begin_read()?;
+    let table = match read_txn.open_table(METADATA) {
+        Ok(table) => table,
+        Err(TableError::TableDoesNotExist(_)) => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+
+    let version = table.get(SCHEMA_VERSION_KEY)?;
+    Ok(version.map(|version| version.value()).unwrap_or(0))
+}
+
+// Bring the database to `SCHEMA_VERSION`, returns the resulting version
+pub fn migrate(db: &redb::Database) -> Result<u64, redb::Error> {
+    run_migrations(db, MIGRATIONS)
+}
+
+// Each migration is committed along with the version it upgrades to,
+// an interrupted upgrade resumes from the last successful migration
+pub(crate) fn run_migrations(
+    db: &redb::Database,
+    migrations: &[Migration],
+) -> Result<u64, redb::Error> {
+    let mut version = schema_version(db)?;
+    let latest = migrations.last().map_or(0, |migration| migration.version);
+    if version > latest {
+        warn!("Database schema version {version} is newer than supported version {latest}");
+        return Ok(version);
+    }
+
+    let current = version;
+    let pending = migrations
+        .iter()
+        .filter(|migration| migration.version > current);
+    for migration in pending {
+        info!(
+            "Migrating database to version {}: {}",
+            migration.version, migration.description
+        );
+
+        let write_txn = db.begin_write()?;
+        (migration.migrate)(&write_txn)?;
+        set_schema_version(&write_txn, migration.version)?;
+        write_txn.commit()?;
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+pub(crate) fn set_schema_version(
+    write_txn: &WriteTransaction,
+    version: u64,
+) -> Result<(), redb::Error> {
+    let mut metadata = write_txn.open_table(METADATA)?;
+    metadata.insert(SCHEMA_VERSION_KEY, version)?;
+    Ok(())
+}
+
+// Names of the entity collections
+pub(crate) fn collections(write_txn: &WriteTransaction) -> Result<Vec<String>, redb::Error> {
+    Ok(write_txn
+        .list_tables()?
+        .map(|table| table.name().to_string())
+        .filter(|name| name != METADATA.name())
+        .collect())
+}
+
+// Rows that can't be decoded are skipped when reading and would fail any later migration
+fn remove_undecodable_rows(write_txn: &WriteTransaction) -> Result<(), redb::Error> {
+    for collection in collections(write_txn)? {
+        let mut table = write_txn.open_table(TableDefinition::<&str, &str>::new(&collection))?;
+        let mut undecodable = vec![];
+        for row in table.iter()? {
+            let (key, value) = row?;
+            let decoded =
+                serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(value.value());
+
+            if decoded.is_err() {
+                undecodable.push(key.value().to_string());
+            }
+        }
+
+        for key in undecodable {
+            warn!("Removing undecodable row '{key}' from '{collection}'");
+            table.remove(key.as_str())?;
+        }
+    }
+
+    Ok(())
+}
+
+// Desktop entries used to be keyed by name, rows sharing a desktop file id are merged
+fn key_desktop_entries_by_id(write_txn: &WriteTransaction) -> Result<(), redb::Error> {
+    // Opening the table would create it
+    let collections = collections(write_txn)?;
+    if !collections
+        .iter()
+        .any(|name| name == desktop_entry::COLLECTION)
+    {
+        return Ok(());
+    }
+
+    let definition = TableDefinition::<&str, &str>::new(desktop_entry::COLLECTION);
+    let mut table = write_txn.open_table(definition)?;
+    let mut rows = vec![];
+    for row in table.iter()? {
+        let (key, value) = row?;
+        let Ok(row) = serde_json::from_str::<Map<String, Value>>(value.value()) else {
+            continue;
+        };
+
+        let id = row
+            .get("path")
+            .and_then(Value::as_str)
+            .and_then(|path| desktop_file_id(Path::new(path)));
+
+        match id {
+            Some(id) => rows.push((key.value().to_string(), id, row)),
+            None => warn!("Keeping desktop entry '{}' without a path", key.value()),
+        }
+    }
+
+    let mut rekeyed: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+    for (key, id, row) in rows {
+        debug!("Moving desktop entry '{key}' to '{id}'");
+        table.remove(key.as_str())?;
+        let row = match rekeyed.remove(&id) {
+            Some(existing) => merge_rows(existing, &row),
+            None => row,
+        };
+        rekeyed.insert(id, row);
+    }
+
+    for (id, row) in rekeyed {
+        let json = serde_json::to_string(&row).expect("Serialization error");
+        table.insert(id.as_str(), json.as_str())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::db::desktop_entry::{self, DesktopEntryEntity};
+    use crate::db::migration::{
+        key_desktop_entries_by_id, run_migrations, schema_version, Migration, METADATA,
+        SCHEMA_VERSION,
+    };
+    use crate::db::plugin::PluginCommandEntity;
+    use crate::db::query::{self, QueryEntity};
+    use crate::db::web::WebEntity;
+    use crate::db::Database;
+    use redb::{ReadableTable, TableDefinition, WriteTransaction};
+    use speculoos::prelude::*;
+    use std::borrow::Cow;
+    use std::path::PathBuf;
+
+    pub(crate) fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("onagre-db-{}-{name}", std::process::id()))
+    }
+
+    pub(crate) fn command(query: &str, weight: u8, last_used: u64) -> PluginCommandEntity<'static> {
+        PluginCommandEntity {
+            query: Cow::Owned(query.to_string()),
+            weight,
+            last_used,
+        }
+    }
+
+    // A database written by onagre before schema versioning, see `fixtures/onagre-db-v0`
+    pub(crate) fn v0_fixture(name: &str) -> PathBuf {
+        let path = temp_path(name);
+        let fixture =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/db/fixtures/onagre-db-v0");
+        std::fs::copy(fixture, &path).unwrap();
+        path
+    }
+
+    fn raw_value(db: &Database, collection: &str, key: &str) -> Option<String> {
+        let read_txn = db.inner.begin_read().unwrap();
+        let table = read_txn
+            .open_table(TableDefinition::<&str, &str>::new(collection))
+            .unwrap();
+        let value = table.get(key).unwrap();
+        value.map(|value| value.value().to_string())
+    }
+
+    #[test]
+    fn should_migrate_v0_fixture() {
+        let path = v0_fixture("migrate");
+        let db = Database::open(&path).unwrap();
+
+        asserting!("Should be at the latest version")
+            .that(&schema_version(&db.inner).unwrap())
+            .is_equal_to(SCHEMA_VERSION);
+
+        let desktop_entries = db.get_all::<DesktopEntryEntity>(desktop_entry::COLLECTION);
+        let names: Vec<_> = desktop_entries
+            .iter()
+            .map(|entry| entry.name.as_ref())
+            .collect();
+        assert_eq!(names, ["Firefox", "Files"]);
+        assert_eq!(desktop_entries[0].weight, 3);
+
+        asserting!("Should key desktop entries by desktop file id")
+            .that(&raw_value(
+                &db,
+                desktop_entry::COLLECTION,
+                "firefox.desktop",
+            ))
+            .is_some();
+
+        asserting!("Should remove name keys")
+            .that(&raw_value(&db, desktop_entry::COLLECTION, "Firefox"))
+            .is_none();
+
+        let queries = db.get_all::<QueryEntity>(query::COLLECTION);
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].query, "cargo build");
+
+        let web = db.get_by_key::<WebEntity>("ddg", "rust redb");
+        asserting!("Should keep web history")
+            .that(&web.map(|web| web.weight))
+            .is_equal_to(Some(2));
+
+        let commands = db.get_all::<PluginCommandEntity>("terminal");
+        assert_eq!(commands.len(), 1);
+
+        asserting!("Should remove undecodable rows")
+            .that(&raw_value(&db, "terminal", "broken"))
+            .is_none();
+
+        asserting!("Should remove truncated rows")
+            .that(&raw_value(&db, query::COLLECTION, "truncated"))
+            .is_none();
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_merge_desktop_entries_sharing_an_id() {
+        let path = temp_path("rekey");
+        let db = redb::Database::create(&path).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        {
+            let definition = TableDefinition::<&str, &str>::new(desktop_entry::COLLECTION);
+            let mut table = write_txn.open_table(definition).unwrap();
+            let rows = [
+                ("Firefox", "/usr/share/applications/firefox.desktop", 3),
+                ("Firefox ESR", "/usr/share/applications/firefox.desktop", 2),
+                (
+                    "Firefox Flatpak",
+                    "/var/lib/flatpak/exports/share/applications/org.mozilla.firefox.desktop",
+                    1,
+                ),
+            ];
+
+            for (name, path, weight) in rows {
+                let row = format!(r#"{{"name":"{name}","path":"{path}","weight":{weight}}}"#);
+                table.insert(name, row.as_str()).unwrap();
+            }
+        }
+        key_desktop_entries_by_id(&write_txn).unwrap();
+        write_txn.commit().unwrap();
+
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn
+            .open_table(TableDefinition::<&str, &str>::new(
+                desktop_entry::COLLECTION,
+            ))
+            .unwrap();
+        let keys: Vec<_> = table
+            .iter()
+            .unwrap()
+            .map(|row| row.unwrap().0.value().to_string())
+            .collect();
+        assert_eq!(keys, ["firefox.desktop", "org.mozilla.firefox.desktop"]);
+
+        let firefox: DesktopEntryEntity = {
+            let row = table.get("firefox.desktop").unwrap().unwrap();
+            serde_json::from_str(row.value()).unwrap()
+        };
+        asserting!("Should sum the weights of merged rows")
+            .that(&firefox.weight)
+            .is_equal_to(5);
+
+        drop(table);
+        drop(read_txn);
+        drop(db);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    fn mark_migrated(write_txn: &WriteTransaction) -> Result<(), redb::Error> {
+        let mut table = write_txn.open_table(TableDefinition::<&str, &str>::new("migrated"))?;
+        let count = table
+            .get("count")?
+            .map(|count| count.value().parse::<u32>().unwrap())
+            .unwrap_or(0);
+        table.insert("count", (count + 1).to_string().as_str())?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_run_each_migration_once() {
+        let path = temp_path("once");
+        let db = redb::Database::create(&path).unwrap();
+        let migrations = [
+            Migration {
+                version: 1,
+                description: "first",
+                migrate: |_| Ok(()),
+            },
+            Migration {
+                version: 2,
+                description: "mark",
+                migrate: mark_migrated,
+            },
+        ];
+
+        asserting!("Should migrate to the last version")
+            .that(&run_migrations(&db, &migrations).unwrap())
+            .is_equal_to(2);
+
+        asserting!("Should not migrate twice")
+            .that(&run_migrations(&db, &migrations).unwrap())
+            .is_equal_to(2);
+
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn
+            .open_table(TableDefinition::<&str, &str>::new("migrated"))
+            .unwrap();
+        assert_eq!(table.get("count").unwrap().unwrap().value(), "1");
+
+        drop(table);
+        drop(read_txn);
+        drop(db);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_not_downgrade_newer_database() {
+        let path = temp_path("newer");
+        let db = redb::Database::create(&path).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut metadata = write_txn.open_table(METADATA).unwrap();
+            metadata
+                .insert(super::SCHEMA_VERSION_KEY, SCHEMA_VERSION + 1)
+                .unwrap();
+        }
+        write_txn.commit().unwrap();
+
+        asserting!("Should keep the newer version")
+            .that(&run_migrations(&db, super::MIGRATIONS).unwrap())
+            .is_equal_to(SCHEMA_VERSION + 1);
+
+        drop(db);
+        std::fs::remove_file(path).unwrap();
+    }
+}
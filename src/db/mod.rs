@@ -1,46 +1,257 @@
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use std::borrow::Cow;
 use std::cmp::Reverse;
+use std::convert::Infallible;
 use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
-use tracing::{debug, trace};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, trace, warn};
 
-use redb::{ReadableTable, TableDefinition};
+use redb::backends::InMemoryBackend;
+use redb::{DatabaseError, ReadableTable, StorageError, TableDefinition, TableError};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::settings::HistoryConfig;
+
+pub mod alias;
 pub mod desktop_entry;
+pub mod history;
+pub mod migration;
+pub mod pinned;
 pub mod plugin;
+pub mod prune;
+pub mod query;
+pub mod ranking;
+pub mod stats;
 pub mod web;
 
-pub static DB: Lazy<Database> = Lazy::new(Database::default);
+// How long to wait for another onagre instance to release the database
+const LOCK_TIMEOUT: Duration = Duration::from_millis(500);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+// Set from the command line, before the database is first used
+pub static DB_OPTIONS: OnceCell<DatabaseOptions> = OnceCell::new();
+
+pub static DB: Lazy<Database> = Lazy::new(|| {
+    let options = DB_OPTIONS.get().cloned().unwrap_or_default();
+    Database::with_options(&options)
+});
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DatabaseOptions {
+    pub location: DatabaseLocation,
+    // History is shown but nothing is recorded
+    pub incognito: bool,
+    pub history: HistoryConfig,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DatabaseLocation {
+    File(PathBuf),
+    Memory,
+}
+
+impl Default for DatabaseLocation {
+    fn default() -> Self {
+        match dirs::data_dir() {
+            Some(path) => DatabaseLocation::File(path.join("onagre-db")),
+            None => {
+                warn!("Data dir not found, history will not be kept");
+                DatabaseLocation::Memory
+            }
+        }
+    }
+}
+
+// `:memory:` selects the in-memory backend, anything else is a path
+impl FromStr for DatabaseLocation {
+    type Err = Infallible;
+
+    fn from_str(location: &str) -> Result<Self, Self::Err> {
+        match location {
+            ":memory:" => Ok(DatabaseLocation::Memory),
+            path => Ok(DatabaseLocation::File(PathBuf::from(path))),
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Database {
     inner: Arc<redb::Database>,
+    incognito: bool,
+    history: HistoryConfig,
 }
 
 impl Default for Database {
     fn default() -> Self {
-        let path = dirs::data_dir().expect("Cannot open data dir");
+        Database::with_options(&DatabaseOptions::default())
+    }
+}
+
+impl Database {
+    // When the file is locked by another instance, falls back to a read-only snapshot of it.
+    // Falls back to an in-memory database when the file can't be opened at all.
+    pub fn with_options(options: &DatabaseOptions) -> Self {
+        let database = match &options.location {
+            DatabaseLocation::File(path) => Database::open_or_snapshot(path, LOCK_TIMEOUT),
+            DatabaseLocation::Memory => {
+                Database::in_memory().expect("failed to create in-memory database")
+            }
+        };
+
+        Database {
+            incognito: options.incognito || database.incognito,
+            history: options.history.clone(),
+            ..database
+        }
+    }
+
+    fn open_or_snapshot(path: &Path, timeout: Duration) -> Self {
+        match Database::open_with_timeout(path, timeout) {
+            Ok(database) => database,
+            Err(redb::Error::DatabaseAlreadyOpen) => {
+                warn!("Database {path:?} is used by another onagre instance, history will not be recorded");
+                Database::snapshot_or_in_memory(path)
+            }
+            Err(err) => {
+                error!("Failed to open database {path:?}, history will not be kept: {err}");
+                Database::in_memory().expect("failed to create in-memory database")
+            }
+        }
+    }
 
-        let path = path.join("onagre-db");
-        let path = path.as_path();
+    // The other instance may be writing while the file is copied,
+    // a snapshot that can't be opened is replaced by an empty in-memory database
+    fn snapshot_or_in_memory(path: &Path) -> Self {
+        match Database::snapshot(path) {
+            Ok(database) => database,
+            Err(err) => {
+                error!("Failed to open a snapshot of {path:?}, history will not be shown: {err}");
+                let database = Database::in_memory().expect("failed to create in-memory database");
+                Database {
+                    incognito: true,
+                    ..database
+                }
+            }
+        }
+    }
+
+    // Open or create the database and upgrade it to the current schema version
+    pub fn open(path: &Path) -> Result<Self, redb::Error> {
+        Database::open_with_timeout(path, LOCK_TIMEOUT)
+    }
 
+    // Wait up to `timeout` for the lock to be released, e.g: by an exiting onagre
+    fn open_with_timeout(path: &Path, timeout: Duration) -> Result<Self, redb::Error> {
         debug!("Opening database {:?}", path);
 
-        let database = match redb::Database::open(path) {
-            Ok(db) => db,
-            Err(_err) => redb::Database::create(path).expect("failed to create database"),
+        let start = Instant::now();
+        let database = loop {
+            match redb::Database::create(path) {
+                Err(DatabaseError::DatabaseAlreadyOpen) if start.elapsed() < timeout => {
+                    debug!("Database {path:?} is locked, retrying");
+                    thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                result => break result?,
+            }
         };
 
-        Database {
+        // Rows left in an older format are still readable when possible
+        if let Err(err) = migration::migrate(&database) {
+            error!("Failed to migrate database {path:?}: {err}");
+        }
+
+        Ok(Database {
             inner: Arc::new(database),
+            incognito: false,
+            history: HistoryConfig::default(),
+        })
+    }
+
+    // A private copy of a locked database, opened in incognito mode since
+    // nothing written to it would outlive the session
+    fn snapshot(path: &Path) -> Result<Self, redb::Error> {
+        let copy = snapshot_path();
+        debug!("Opening a snapshot of {path:?} at {copy:?}");
+
+        // Never reuse an existing file or follow a symlink left in a shared directory
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&copy)?;
+        let copied = File::open(path).and_then(|mut source| io::copy(&mut source, &mut file));
+        drop(file);
+
+        let database = match copied {
+            // redb asserts on some truncated files instead of returning an error
+            Ok(_) => panic::catch_unwind(|| redb::Database::create(&copy))
+                .unwrap_or_else(|_| {
+                    Err(DatabaseError::Storage(StorageError::Corrupted(
+                        "truncated database snapshot".to_string(),
+                    )))
+                })
+                .map_err(redb::Error::from),
+            Err(err) => Err(err.into()),
+        };
+
+        // The file stays readable as long as the database is open
+        if let Err(err) = std::fs::remove_file(&copy) {
+            warn!("Failed to remove database snapshot {copy:?}: {err}");
+        }
+
+        let database = database?;
+        if let Err(err) = migration::migrate(&database) {
+            error!("Failed to migrate database snapshot: {err}");
         }
+
+        Ok(Database {
+            inner: Arc::new(database),
+            incognito: true,
+            history: HistoryConfig::default(),
+        })
+    }
+
+    // Nothing is written to disk, the history is lost on exit
+    pub fn in_memory() -> Result<Self, redb::Error> {
+        debug!("Opening in-memory database");
+        let database = redb::Database::builder().create_with_backend(InMemoryBackend::new())?;
+        migration::migrate(&database)?;
+
+        Ok(Database {
+            inner: Arc::new(database),
+            incognito: false,
+            history: HistoryConfig::default(),
+        })
+    }
+
+    pub fn is_incognito(&self) -> bool {
+        self.incognito
+    }
+
+    // Insert a history entry and enforce the collection limits, unless in incognito mode
+    pub fn record<'a, T>(&self, collection: &str, entity: &T) -> Result<(), redb::Error>
+    where
+        T: Sized + Entity<'a> + Serialize + Debug,
+    {
+        if self.incognito {
+            debug!("Incognito mode, not recording {entity:?} into '{collection}'");
+            return Ok(());
+        }
+
+        self.insert(collection, entity)?;
+        self.prune_collection(collection, &entity.get_key())?;
+        Ok(())
     }
-}
 
-impl Database {
     pub fn insert<'a, T>(&self, collection: &str, entity: &T) -> Result<(), redb::Error>
     where
         T: Sized + Entity<'a> + Serialize,
@@ -57,19 +268,39 @@ impl Database {
         Ok(())
     }
 
+    pub fn remove(&self, collection: &str, key: &str) -> Result<(), redb::Error> {
+        let db = self.inner.clone();
+        let write_tnx = db.begin_write()?;
+        {
+            let definition = TableDefinition::<&str, &str>::new(collection);
+            let mut table = write_tnx.open_table(definition)?;
+            table.remove(key)?;
+        }
+        write_tnx.commit()?;
+        Ok(())
+    }
+
     pub fn get_by_key<'a, T>(&self, collection: &str, key: &str) -> Option<T>
     where
         T: Entity<'a> + DeserializeOwned,
     {
         let definition = TableDefinition::<&str, &str>::new(collection);
-        let db = self.inner.clone();
-        let Ok(read_txn) = db.begin_write() else {
-            return None;
+        let read_txn = self
+            .inner
+            .begin_read()
+            .map_err(|err| error!("Failed to read database: {err}"))
+            .ok()?;
+
+        let table = match read_txn.open_table(definition) {
+            Ok(table) => table,
+            // Nothing was recorded in this collection yet
+            Err(TableError::TableDoesNotExist(_)) => return None,
+            Err(err) => {
+                error!("Failed to open '{collection}': {err}");
+                return None;
+            }
         };
 
-        let table = read_txn
-            .open_table(definition)
-            .expect("failed to open database");
         table
             .get(key)
             .ok()
@@ -83,14 +314,33 @@ impl Database {
         T: Entity<'a> + DeserializeOwned + Debug,
     {
         let definition = TableDefinition::<&str, &str>::new(collection);
-        let db = self.inner.clone();
-        let Ok(read_txn) = db.begin_write() else {
-            return vec![];
+        let read_txn = match self.inner.begin_read() {
+            Ok(read_txn) => read_txn,
+            Err(err) => {
+                error!("Failed to read database: {err}");
+                return vec![];
+            }
+        };
+
+        let table = match read_txn.open_table(definition) {
+            Ok(table) => table,
+            // Nothing was recorded in this collection yet
+            Err(TableError::TableDoesNotExist(_)) => return vec![],
+            Err(err) => {
+                error!("Failed to open '{collection}': {err}");
+                return vec![];
+            }
         };
-        let table = read_txn.open_table(definition).unwrap();
-        let mut results: Vec<T> = table
-            .iter()
-            .unwrap()
+
+        let rows = match table.iter() {
+            Ok(rows) => rows,
+            Err(err) => {
+                error!("Failed to read '{collection}': {err}");
+                return vec![];
+            }
+        };
+
+        let mut results: Vec<T> = rows
             .filter_map(Result::ok)
             .map(|(_key, value)| serde_json::from_str(value.value()))
             .flat_map(Result::ok)
@@ -106,7 +356,150 @@ impl Database {
     }
 }
 
+// A user private directory when available, unique per snapshot
+fn snapshot_path() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.subsec_nanos())
+        .unwrap_or_default();
+
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(format!("onagre-db-snapshot-{}-{nanos}", std::process::id()))
+}
+
+// Seconds since the unix epoch
+pub(crate) fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
 pub trait Entity<'a> {
     fn get_key(&self) -> Cow<'a, str>;
     fn get_weight(&self) -> u8;
+    // Seconds since the unix epoch, 0 when unknown
+    fn get_last_used(&self) -> u64 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::desktop_entry::{self, DesktopEntryEntity};
+    use crate::db::migration::test::{temp_path, v0_fixture};
+    use crate::db::plugin::PluginCommandEntity;
+    use crate::db::query::{self, QueryEntity};
+    use crate::db::{Database, DatabaseLocation, DatabaseOptions};
+    use speculoos::prelude::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn should_parse_database_location() {
+        assert_eq!(":memory:".parse(), Ok(DatabaseLocation::Memory));
+        assert_eq!(
+            "/tmp/onagre-db".parse(),
+            Ok(DatabaseLocation::File(PathBuf::from("/tmp/onagre-db")))
+        );
+    }
+
+    #[test]
+    fn should_record_history_in_memory() {
+        let db = Database::in_memory().unwrap();
+        PluginCommandEntity::persist("terminal", "htop", &db);
+        PluginCommandEntity::persist("terminal", "htop", &db);
+
+        let htop = db.get_by_key::<PluginCommandEntity>("terminal", "htop");
+        asserting!("Should record launches")
+            .that(&htop.map(|command| command.weight))
+            .is_equal_to(Some(1));
+    }
+
+    #[test]
+    fn should_not_record_history_in_incognito_mode() {
+        let db = Database::with_options(&DatabaseOptions {
+            location: DatabaseLocation::Memory,
+            incognito: true,
+            ..Default::default()
+        });
+
+        PluginCommandEntity::persist("terminal", "htop", &db);
+        QueryEntity::persist("cargo build", &db);
+
+        asserting!("Should not record plugin commands")
+            .that(&db.get_by_key::<PluginCommandEntity>("terminal", "htop"))
+            .is_none();
+
+        asserting!("Should not record queries")
+            .that(&db.get_all::<QueryEntity>(query::COLLECTION))
+            .is_empty();
+    }
+
+    #[test]
+    fn should_open_snapshot_of_locked_database() {
+        let path = v0_fixture("locked");
+        let first = Database::open(&path).unwrap();
+        let second = Database::open_or_snapshot(&path, Duration::from_millis(100));
+
+        asserting!("Should not record into the snapshot")
+            .that(&second.is_incognito())
+            .is_true();
+
+        let firefox =
+            second.get_by_key::<DesktopEntryEntity>(desktop_entry::COLLECTION, "firefox.desktop");
+        asserting!("Should read the history from the snapshot")
+            .that(&firefox.map(|entry| entry.weight))
+            .is_equal_to(Some(3));
+
+        PluginCommandEntity::persist("terminal", "htop", &first);
+        asserting!("Should keep recording in the first instance")
+            .that(&first.get_by_key::<PluginCommandEntity>("terminal", "htop"))
+            .is_some();
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_fall_back_to_memory_on_broken_snapshot() {
+        let path = v0_fixture("partial");
+        let content = std::fs::read(&path).unwrap();
+        let corrupt = temp_path("corrupt");
+        std::fs::write(&corrupt, b"not a database").unwrap();
+        // As if copied while the other instance was writing
+        let partial = temp_path("partial-copy");
+        std::fs::write(&partial, &content[..content.len() / 2]).unwrap();
+
+        for path in [&corrupt, &partial] {
+            let db = Database::snapshot_or_in_memory(path);
+            asserting!("Should never record into a fallback database")
+                .that(&db.is_incognito())
+                .is_true();
+
+            // Whatever could be read, the database must be usable
+            let _ = db.get_all::<DesktopEntryEntity>(desktop_entry::COLLECTION);
+        }
+
+        asserting!("Should fall back on a missing file")
+            .that(&Database::snapshot_or_in_memory(&temp_path("missing")).is_incognito())
+            .is_true();
+
+        for path in [path, corrupt, partial] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn should_read_missing_collection() {
+        let db = Database::in_memory().unwrap();
+
+        asserting!("Should not fail on a collection that was never written")
+            .that(&db.get_all::<QueryEntity>("missing"))
+            .is_empty();
+
+        asserting!("Should not find any row")
+            .that(&db.get_by_key::<QueryEntity>("missing", "query"))
+            .is_none();
+    }
 }
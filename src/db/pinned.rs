@@ -0,0 +1,54 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::{timestamp, Database, Entity};
+
+pub const COLLECTION: &str = "pinned";
+
+// A desktop entry pinned at the top of the history, identified by its desktop file id
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PinnedEntity<'a> {
+    pub id: Cow<'a, str>,
+    // Seconds since the unix epoch, pinned entries are displayed in pinning order
+    pub pinned_at: u64,
+}
+
+impl<'a> Entity<'a> for PinnedEntity<'a> {
+    fn get_key(&self) -> Cow<'a, str> {
+        self.id.clone()
+    }
+
+    fn get_weight(&self) -> u8 {
+        0
+    }
+}
+
+impl PinnedEntity<'_> {
+    // Pin the entry if it's not pinned yet, unpin it otherwise
+    pub fn toggle(id: &str, db: &Database) {
+        if db.get_by_key::<PinnedEntity>(COLLECTION, id).is_some() {
+            db.remove(COLLECTION, id)
+                .expect("Unable to remove pinned entry");
+            return;
+        }
+
+        let entity = PinnedEntity {
+            id: Cow::Borrowed(id),
+            pinned_at: timestamp(),
+        };
+
+        db.insert(COLLECTION, &entity)
+            .expect("Unable to insert pinned entry");
+    }
+
+    // Pinned desktop file ids, in pinning order
+    pub fn all(db: &Database) -> Vec<String> {
+        let mut pinned = db.get_all::<PinnedEntity>(COLLECTION);
+        pinned.sort_by_key(|entity| entity.pinned_at);
+        pinned
+            .into_iter()
+            .map(|entity| entity.id.into_owned())
+            .collect()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::db::{Database, Entity};
+use crate::db::{timestamp, Database, Entity};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
@@ -6,6 +6,9 @@ use std::borrow::Cow;
 pub struct PluginCommandEntity<'a> {
     pub(crate) query: Cow<'a, str>,
     pub weight: u8,
+    // Seconds since the unix epoch
+    #[serde(default)]
+    pub last_used: u64,
 }
 
 impl PluginCommandEntity<'_> {
@@ -19,9 +22,10 @@ impl PluginCommandEntity<'_> {
         let entity = PluginCommandEntity {
             query: Cow::Borrowed(query),
             weight,
+            last_used: timestamp(),
         };
 
-        db.insert(collection, &entity)
+        db.record(collection, &entity)
             .expect("Unable to insert terminal cache entry");
     }
 }
@@ -34,4 +38,8 @@ impl<'a> Entity<'a> for PluginCommandEntity<'a> {
     fn get_weight(&self) -> u8 {
         self.weight
     }
+
+    fn get_last_used(&self) -> u64 {
+        self.last_used
+    }
 }
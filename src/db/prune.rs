@@ -0,0 +1,200 @@
+use redb::{ReadableTable, TableDefinition, WriteTransaction};
+use serde_json::{Map, Value};
+use tracing::debug;
+
+use crate::db::{alias, migration, pinned, timestamp, Database};
+use crate::settings::HistoryLimits;
+
+pub(crate) const DAY: u64 = 24 * 60 * 60;
+
+// Collections holding user choices rather than history, never pruned
+const KEPT_COLLECTIONS: &[&str] = &[pinned::COLLECTION, alias::COLLECTION];
+
+struct Row {
+    key: String,
+    weight: u64,
+    last_used: u64,
+}
+
+impl Database {
+    // Enforce the configured limits on every history collection, returns the number of removed rows
+    pub fn prune(&self) -> Result<usize, redb::Error> {
+        let now = timestamp();
+        let write_txn = self.inner.begin_write()?;
+        let mut removed = 0;
+        for collection in migration::collections(&write_txn)? {
+            let limits = self.history.limits(&collection);
+            removed += prune_collection(&write_txn, &collection, limits, now, None)?;
+        }
+
+        write_txn.commit()?;
+        Ok(removed)
+    }
+
+    // Enforce the limits of a single collection, after `recorded` was recorded
+    pub(crate) fn prune_collection(
+        &self,
+        collection: &str,
+        recorded: &str,
+    ) -> Result<usize, redb::Error> {
+        let limits = self.history.limits(collection);
+        if limits == HistoryLimits::default() {
+            return Ok(0);
+        }
+
+        let write_txn = self.inner.begin_write()?;
+        let removed =
+            prune_collection(&write_txn, collection, limits, timestamp(), Some(recorded))?;
+        write_txn.commit()?;
+        Ok(removed)
+    }
+}
+
+// Drop rows unused for more than `max_age_days`, then the lowest scoring rows
+// above `max_entries`. Rows recorded without a timestamp never expire,
+// the `recorded` row is never evicted to make room for the others.
+pub(crate) fn prune_collection(
+    write_txn: &WriteTransaction,
+    collection: &str,
+    limits: HistoryLimits,
+    now: u64,
+    recorded: Option<&str>,
+) -> Result<usize, redb::Error> {
+    if KEPT_COLLECTIONS.contains(&collection) || limits == HistoryLimits::default() {
+        return Ok(0);
+    }
+
+    let mut table = write_txn.open_table(TableDefinition::<&str, &str>::new(collection))?;
+    let mut rows = vec![];
+    for row in table.iter()? {
+        let (key, value) = row?;
+        let value = serde_json::from_str::<Map<String, Value>>(value.value()).unwrap_or_default();
+        let field = |name: &str| value.get(name).and_then(Value::as_u64).unwrap_or(0);
+        rows.push(Row {
+            key: key.value().to_string(),
+            weight: field("weight"),
+            last_used: field("last_used"),
+        });
+    }
+
+    let (mut removed, mut kept): (Vec<_>, Vec<_>) =
+        rows.into_iter().partition(|row| match limits.max_age_days {
+            Some(days) if row.last_used > 0 => now.saturating_sub(row.last_used) > days * DAY,
+            _ => false,
+        });
+
+    if let Some(max_entries) = limits.max_entries {
+        // Least launched first, the least recently used among equals,
+        // a new entry starts with no weight but must survive its own insertion
+        kept.sort_by_key(|row| {
+            let is_recorded = recorded == Some(row.key.as_str());
+            (is_recorded, row.weight, row.last_used)
+        });
+        let excess = kept.len().saturating_sub(max_entries);
+        removed.extend(kept.drain(..excess));
+    }
+
+    for row in &removed {
+        debug!("Pruning '{}' from '{collection}'", row.key);
+        table.remove(row.key.as_str())?;
+    }
+
+    Ok(removed.len())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::migration::test::command;
+    use crate::db::plugin::PluginCommandEntity;
+    use crate::db::prune::{prune_collection, DAY};
+    use crate::db::{Database, DatabaseLocation, DatabaseOptions};
+    use crate::settings::{HistoryConfig, HistoryLimits};
+    use speculoos::prelude::*;
+    use std::collections::HashMap;
+
+    fn queries(db: &Database) -> Vec<String> {
+        db.get_all::<PluginCommandEntity>("terminal")
+            .into_iter()
+            .map(|command| command.query.into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn should_drop_expired_and_lowest_scoring_rows() {
+        let now = 100 * DAY;
+        let db = Database::in_memory().unwrap();
+        db.insert("terminal", &command("htop", 9, now - 40 * DAY))
+            .unwrap();
+        db.insert("terminal", &command("top", 1, now - DAY))
+            .unwrap();
+        db.insert("terminal", &command("btop", 1, now)).unwrap();
+        db.insert("terminal", &command("cargo test", 5, now))
+            .unwrap();
+        db.insert("terminal", &command("old", 0, 0)).unwrap();
+
+        let limits = HistoryLimits {
+            max_entries: Some(2),
+            max_age_days: Some(30),
+        };
+
+        let write_txn = db.inner.begin_write().unwrap();
+        let removed = prune_collection(&write_txn, "terminal", limits, now, None).unwrap();
+        write_txn.commit().unwrap();
+
+        asserting!("Should remove expired and excess rows")
+            .that(&removed)
+            .is_equal_to(3);
+
+        assert_eq!(queries(&db), ["cargo test", "btop"]);
+    }
+
+    #[test]
+    fn should_prune_on_record() {
+        let db = Database::with_options(&DatabaseOptions {
+            location: DatabaseLocation::Memory,
+            incognito: false,
+            history: HistoryConfig {
+                collections: HashMap::from([(
+                    "terminal".to_string(),
+                    HistoryLimits {
+                        max_entries: Some(1),
+                        max_age_days: None,
+                    },
+                )]),
+                ..Default::default()
+            },
+        });
+
+        PluginCommandEntity::persist("terminal", "htop", &db);
+        PluginCommandEntity::persist("terminal", "htop", &db);
+        PluginCommandEntity::persist("terminal", "top", &db);
+
+        assert_eq!(queries(&db), ["top"]);
+    }
+
+    #[test]
+    fn should_keep_new_entry_in_full_collection() {
+        let db = Database::with_options(&DatabaseOptions {
+            location: DatabaseLocation::Memory,
+            incognito: false,
+            history: HistoryConfig {
+                collections: HashMap::from([(
+                    "terminal".to_string(),
+                    HistoryLimits {
+                        max_entries: Some(2),
+                        max_age_days: None,
+                    },
+                )]),
+                ..Default::default()
+            },
+        });
+
+        for query in ["htop", "htop", "htop", "btop", "btop", "top"] {
+            PluginCommandEntity::persist("terminal", query, &db);
+        }
+
+        let mut queries = queries(&db);
+        queries.sort();
+        assert_eq!(queries, ["htop", "top"]);
+    }
+}
@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::{timestamp, Database, Entity};
+
+pub const COLLECTION: &str = "queries";
+
+// A query submitted from the search bar, including the plugin prefix if any
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QueryEntity<'a> {
+    pub query: Cow<'a, str>,
+    pub weight: u8,
+    // Seconds since the unix epoch
+    #[serde(default)]
+    pub last_used: u64,
+}
+
+impl<'a> Entity<'a> for QueryEntity<'a> {
+    fn get_key(&self) -> Cow<'a, str> {
+        self.query.clone()
+    }
+
+    fn get_weight(&self) -> u8 {
+        self.weight
+    }
+
+    fn get_last_used(&self) -> u64 {
+        self.last_used
+    }
+}
+
+impl QueryEntity<'_> {
+    pub fn persist(query: &str, db: &Database) {
+        Self::record(query, db, true);
+    }
+
+    // A recalled query was counted when it was first submitted, only its usage time changes
+    pub fn touch(query: &str, db: &Database) {
+        Self::record(query, db, false);
+    }
+
+    fn record(query: &str, db: &Database, submitted: bool) {
+        let weight = match db.get_by_key::<QueryEntity>(COLLECTION, query) {
+            Some(entity) if submitted => entity.weight.saturating_add(1),
+            Some(entity) => entity.weight,
+            None => 0,
+        };
+
+        let entity = QueryEntity {
+            query: Cow::Borrowed(query),
+            weight,
+            last_used: timestamp(),
+        };
+
+        db.record(COLLECTION, &entity)
+            .expect("Unable to insert query history entry");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::query::{QueryEntity, COLLECTION};
+    use crate::db::Database;
+    use speculoos::prelude::*;
+    use std::borrow::Cow;
+
+    fn weight(db: &Database, query: &str) -> Option<u8> {
+        db.get_by_key::<QueryEntity>(COLLECTION, query)
+            .map(|entity| entity.weight)
+    }
+
+    #[test]
+    fn should_count_submitted_queries() {
+        let db = Database::in_memory().unwrap();
+
+        QueryEntity::persist("cargo build", &db);
+        QueryEntity::persist("cargo build", &db);
+        QueryEntity::persist("htop", &db);
+
+        assert_eq!(weight(&db, "cargo build"), Some(1));
+        assert_eq!(weight(&db, "htop"), Some(0));
+    }
+
+    #[test]
+    fn should_only_update_usage_of_recalled_queries() {
+        let db = Database::in_memory().unwrap();
+        let entity = QueryEntity {
+            query: Cow::Borrowed("cargo build"),
+            weight: 3,
+            last_used: 0,
+        };
+        db.insert(COLLECTION, &entity).unwrap();
+
+        QueryEntity::touch("cargo build", &db);
+
+        let entity = db.get_by_key::<QueryEntity>(COLLECTION, "cargo build");
+        asserting!("Should keep the weight and refresh the usage time")
+            .that(&entity.map(|entity| (entity.weight, entity.last_used > 0)))
+            .is_equal_to(Some((3, true)));
+    }
+}
@@ -0,0 +1,239 @@
+use chrono::{DateTime, Datelike, FixedOffset, Local, TimeZone, Timelike, Weekday};
+
+use crate::db::desktop_entry::DesktopEntryEntity;
+
+// Launches further than this many hours from the current hour don't count
+const HOUR_WINDOW: f64 = 3.0;
+// Older launches count half as much every four weeks
+const HALF_LIFE_DAYS: f64 = 28.0;
+// Share of the overall launch count in the score, keeps frequent entries
+// without recorded launch times near the top
+const FREQUENCY_WEIGHT: f64 = 0.1;
+
+// The current local time, injectable for tests
+pub trait Clock {
+    fn now(&self) -> DateTime<FixedOffset>;
+
+    // Local time of a past launch, with the UTC offset in effect back then
+    fn local(&self, timestamp: i64) -> Option<DateTime<FixedOffset>>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        Local::now().into()
+    }
+
+    fn local(&self, timestamp: i64) -> Option<DateTime<FixedOffset>> {
+        Local.timestamp_opt(timestamp, 0).single().map(Into::into)
+    }
+}
+
+// Sort the history by score, highest first, favoring entries usually
+// launched around this hour on this kind of day
+pub fn rank_by_time<'a>(
+    entries: Vec<DesktopEntryEntity<'a>>,
+    clock: &dyn Clock,
+) -> Vec<DesktopEntryEntity<'a>> {
+    let now = clock.now();
+    let mut scored: Vec<_> = entries
+        .into_iter()
+        .map(|entry| (score(&entry, &now, clock), entry))
+        .collect();
+
+    // Stable, equal scores keep the weight order
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+pub fn score(entry: &DesktopEntryEntity, now: &DateTime<FixedOffset>, clock: &dyn Clock) -> f64 {
+    // Launches before a DST change keep their own offset, 09:00 stays 09:00
+    let context: f64 = entry
+        .launches
+        .iter()
+        .filter_map(|launch| clock.local(*launch as i64))
+        .map(|launch| similarity(&launch, now) * decay(&launch, now))
+        .sum();
+
+    context + FREQUENCY_WEIGHT * (entry.weight as f64 + 1.0)
+}
+
+// 1.0 for a launch at the same hour on the same weekday, 0.0 outside the hour window
+fn similarity(launch: &DateTime<FixedOffset>, now: &DateTime<FixedOffset>) -> f64 {
+    let minutes = |time: &DateTime<FixedOffset>| (time.hour() * 60 + time.minute()) as f64;
+    let distance = (minutes(launch) - minutes(now)).abs();
+    // Around midnight, 23:00 is one hour from 00:00
+    let hours = distance.min(24.0 * 60.0 - distance) / 60.0;
+    let hour = (1.0 - hours / HOUR_WINDOW).max(0.0);
+
+    let day = if launch.weekday() == now.weekday() {
+        1.0
+    } else if is_weekend(launch.weekday()) == is_weekend(now.weekday()) {
+        0.5
+    } else {
+        0.0
+    };
+
+    hour * (0.5 + 0.5 * day)
+}
+
+fn decay(launch: &DateTime<FixedOffset>, now: &DateTime<FixedOffset>) -> f64 {
+    let days = (now.timestamp() - launch.timestamp()).max(0) as f64 / (24.0 * 60.0 * 60.0);
+    0.5_f64.powf(days / HALF_LIFE_DAYS)
+}
+
+fn is_weekend(weekday: Weekday) -> bool {
+    matches!(weekday, Weekday::Sat | Weekday::Sun)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::desktop_entry::test::entity;
+    use crate::db::desktop_entry::DesktopEntryEntity;
+    use crate::db::ranking::{rank_by_time, Clock};
+    use chrono::{DateTime, Duration, FixedOffset, TimeZone};
+    use std::borrow::Cow;
+
+    struct FixedClock(DateTime<FixedOffset>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<FixedOffset> {
+            self.0
+        }
+
+        fn local(&self, timestamp: i64) -> Option<DateTime<FixedOffset>> {
+            self.0.offset().timestamp_opt(timestamp, 0).single()
+        }
+    }
+
+    // Central European time, summer time ends on 2026-10-25 at 03:00
+    struct ParisClock(DateTime<FixedOffset>);
+
+    impl Clock for ParisClock {
+        fn now(&self) -> DateTime<FixedOffset> {
+            self.0
+        }
+
+        fn local(&self, timestamp: i64) -> Option<DateTime<FixedOffset>> {
+            let hours = if timestamp < at("2026-10-25T01:00:00Z").timestamp() {
+                2
+            } else {
+                1
+            };
+            FixedOffset::east_opt(hours * 3600)?
+                .timestamp_opt(timestamp, 0)
+                .single()
+        }
+    }
+
+    fn at(time: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(time).unwrap()
+    }
+
+    // Launched at `time` on each of the `days` previous days
+    fn launched(id: &str, weight: u8, time: &str, days: i64) -> DesktopEntryEntity<'static> {
+        let time = at(time);
+        DesktopEntryEntity {
+            launches: (1..=days)
+                .map(|day| (time - Duration::days(day)).timestamp() as u64)
+                .collect(),
+            ..entity(id, weight)
+        }
+    }
+
+    fn names(entries: &[DesktopEntryEntity]) -> Vec<String> {
+        entries.iter().map(|entry| entry.name.to_string()).collect()
+    }
+
+    fn history() -> Vec<DesktopEntryEntity<'static>> {
+        vec![
+            launched("spotify.desktop", 30, "2026-10-19T20:00:00+02:00", 14),
+            launched("thunderbird.desktop", 20, "2026-10-19T08:30:00+02:00", 14),
+            entity("firefox.desktop", 10),
+        ]
+    }
+
+    #[test]
+    fn should_favor_entries_launched_at_this_hour() {
+        let morning = FixedClock(at("2026-10-19T09:00:00+02:00"));
+        let evening = FixedClock(at("2026-10-19T21:00:00+02:00"));
+
+        assert_eq!(
+            names(&rank_by_time(history(), &morning)),
+            ["thunderbird.desktop", "spotify.desktop", "firefox.desktop"]
+        );
+        assert_eq!(
+            names(&rank_by_time(history(), &evening)),
+            ["spotify.desktop", "thunderbird.desktop", "firefox.desktop"]
+        );
+    }
+
+    #[test]
+    fn should_favor_entries_launched_on_this_kind_of_day() {
+        // Both launched at 10:00, during the week or on sundays
+        let weekday = DesktopEntryEntity {
+            launches: ["2026-10-12T10:00:00+02:00", "2026-10-13T10:00:00+02:00"]
+                .iter()
+                .map(|time| at(time).timestamp() as u64)
+                .collect(),
+            ..entity("slack.desktop", 5)
+        };
+        let weekend = DesktopEntryEntity {
+            name: Cow::Borrowed("steam.desktop"),
+            launches: ["2026-10-11T10:00:00+02:00", "2026-10-04T10:00:00+02:00"]
+                .iter()
+                .map(|time| at(time).timestamp() as u64)
+                .collect(),
+            ..weekday.clone()
+        };
+
+        let saturday = FixedClock(at("2026-10-17T10:00:00+02:00"));
+        let monday = FixedClock(at("2026-10-19T10:00:00+02:00"));
+
+        assert_eq!(
+            names(&rank_by_time(
+                vec![weekday.clone(), weekend.clone()],
+                &saturday
+            )),
+            ["steam.desktop", "slack.desktop"]
+        );
+        assert_eq!(
+            names(&rank_by_time(vec![weekend, weekday], &monday)),
+            ["slack.desktop", "steam.desktop"]
+        );
+    }
+
+    #[test]
+    fn should_keep_weight_order_without_launch_times() {
+        let clock = FixedClock(at("2026-10-19T09:00:00+02:00"));
+        let entries = vec![entity("firefox.desktop", 10), entity("foot.desktop", 2)];
+
+        assert_eq!(
+            names(&rank_by_time(entries, &clock)),
+            ["firefox.desktop", "foot.desktop"]
+        );
+    }
+
+    #[test]
+    fn should_use_launch_time_offset_across_dst() {
+        // Both launched on friday, 09:00 and 10:00 summer time
+        let nine = DesktopEntryEntity {
+            launches: vec![at("2026-10-23T09:00:00+02:00").timestamp() as u64],
+            ..entity("thunderbird.desktop", 5)
+        };
+        let ten = DesktopEntryEntity {
+            name: Cow::Borrowed("slack.desktop"),
+            launches: vec![at("2026-10-23T10:00:00+02:00").timestamp() as u64],
+            ..nine.clone()
+        };
+
+        // 09:00 winter time, 10:00 summer time would be 09:00 with the current offset
+        let monday = ParisClock(at("2026-10-26T09:00:00+01:00"));
+
+        assert_eq!(
+            names(&rank_by_time(vec![ten, nine], &monday)),
+            ["thunderbird.desktop", "slack.desktop"]
+        );
+    }
+}
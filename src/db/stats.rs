@@ -0,0 +1,352 @@
+use std::fmt::{self, Debug};
+use std::io::Write;
+
+use redb::TableHandle;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::db::desktop_entry::{self, DesktopEntryEntity};
+use crate::db::plugin::PluginCommandEntity;
+use crate::db::query::{self, QueryEntity};
+use crate::db::{alias, migration, pinned, Database, Entity};
+
+// Collections holding user choices rather than usage
+const IGNORED_COLLECTIONS: &[&str] = &[pinned::COLLECTION, alias::COLLECTION];
+
+#[derive(Debug, Default, Clone)]
+pub struct StatsFilter {
+    // Every collection when empty
+    pub collections: Vec<String>,
+    // Only entries last used less than this many seconds ago
+    pub since: Option<u64>,
+    // Only entries last used more than this many seconds ago
+    pub until: Option<u64>,
+    // Number of entries listed per collection
+    pub limit: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CollectionStats {
+    pub collection: String,
+    pub entries: usize,
+    pub launches: u64,
+    pub top: Vec<EntryStats>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct EntryStats {
+    pub name: String,
+    // Capped at 256, see `launches`
+    pub launches: u64,
+    // Seconds since the unix epoch
+    pub last_used: Option<u64>,
+}
+
+impl Database {
+    pub fn stats(
+        &self,
+        filter: &StatsFilter,
+        now: u64,
+    ) -> Result<Vec<CollectionStats>, redb::Error> {
+        let read_txn = self.inner.begin_read()?;
+        let collections: Vec<String> = read_txn
+            .list_tables()?
+            .map(|table| table.name().to_string())
+            .filter(|name| name != migration::METADATA.name())
+            .filter(|name| !IGNORED_COLLECTIONS.contains(&name.as_str()))
+            .filter(|name| filter.collections.is_empty() || filter.collections.contains(name))
+            .collect();
+        drop(read_txn);
+
+        Ok(collections
+            .into_iter()
+            .map(|collection| match collection.as_str() {
+                // Names are ambiguous when an application is installed several times
+                desktop_entry::COLLECTION => self.collection_stats::<DesktopEntryEntity, _>(
+                    collection,
+                    filter,
+                    now,
+                    |entry| entry.get_key().to_string(),
+                ),
+                query::COLLECTION => {
+                    self.collection_stats::<QueryEntity, _>(collection, filter, now, |query| {
+                        query.query.to_string()
+                    })
+                }
+                // Web and plugin history both store the query
+                _ => self.collection_stats::<PluginCommandEntity, _>(
+                    collection,
+                    filter,
+                    now,
+                    |command| command.query.to_string(),
+                ),
+            })
+            .collect())
+    }
+
+    fn collection_stats<'a, T, F>(
+        &self,
+        collection: String,
+        filter: &StatsFilter,
+        now: u64,
+        name: F,
+    ) -> CollectionStats
+    where
+        T: Entity<'a> + DeserializeOwned + Debug,
+        F: Fn(&T) -> String,
+    {
+        let in_range = |entity: &T| {
+            let last_used = entity.get_last_used();
+            let age = now.saturating_sub(last_used);
+            let has_range = filter.since.is_some() || filter.until.is_some();
+            (!has_range || last_used > 0)
+                && filter.since.is_none_or(|since| age <= since)
+                && filter.until.is_none_or(|until| age >= until)
+        };
+
+        // Sorted by weight
+        let entities: Vec<T> = self
+            .get_all::<T>(&collection)
+            .into_iter()
+            .filter(in_range)
+            .collect();
+
+        let top = entities
+            .iter()
+            .take(filter.limit)
+            .map(|entity| EntryStats {
+                name: name(entity),
+                launches: launches(entity),
+                last_used: Some(entity.get_last_used()).filter(|last_used| *last_used > 0),
+            })
+            .collect();
+
+        CollectionStats {
+            entries: entities.len(),
+            launches: entities.iter().map(launches).sum(),
+            top,
+            collection,
+        }
+    }
+}
+
+// The weight is incremented on each launch after the first one,
+// it saturates at `u8::MAX` so counts stop at 256
+fn launches<'a, T: Entity<'a>>(entity: &T) -> u64 {
+    entity.get_weight() as u64 + 1
+}
+
+// Parse a duration such as `30m`, `12h`, `7d` or `2w` into seconds
+pub fn parse_age(age: &str) -> Result<u64, String> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (amount, unit) = age.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration '{age}', expected e.g: '7d'"))?;
+
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        unit => {
+            return Err(format!(
+                "unknown duration unit '{unit}', expected s, m, h, d or w"
+            ))
+        }
+    };
+
+    amount
+        .checked_mul(unit)
+        .ok_or_else(|| format!("duration '{age}' is too large"))
+}
+
+pub fn write_json<W: Write>(stats: &[CollectionStats], writer: W) -> anyhow::Result<()> {
+    serde_json::to_writer_pretty(writer, stats)?;
+    Ok(())
+}
+
+pub fn write_table<W: Write>(
+    stats: &[CollectionStats],
+    now: u64,
+    mut writer: W,
+) -> anyhow::Result<()> {
+    for collection in stats {
+        writeln!(
+            writer,
+            "{}: {} entries, {} launches",
+            collection.collection, collection.entries, collection.launches
+        )?;
+
+        if collection.top.is_empty() {
+            writeln!(writer)?;
+            continue;
+        }
+
+        writeln!(writer, "  {:>8}  {:>10}  NAME", "LAUNCHES", "LAST USED")?;
+        for entry in &collection.top {
+            let last_used = match entry.last_used {
+                Some(last_used) => Age(now.saturating_sub(last_used)).to_string(),
+                None => "-".to_string(),
+            };
+
+            writeln!(
+                writer,
+                "  {:>8}  {:>10}  {}",
+                entry.launches, last_used, entry.name
+            )?;
+        }
+
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+// A human readable age, in seconds
+struct Age(u64);
+
+impl fmt::Display for Age {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let age = match self.0 {
+            seconds if seconds < 60 => "just now".to_string(),
+            seconds if seconds < 60 * 60 => format!("{}m ago", seconds / 60),
+            seconds if seconds < 24 * 60 * 60 => format!("{}h ago", seconds / (60 * 60)),
+            seconds => format!("{}d ago", seconds / (24 * 60 * 60)),
+        };
+
+        f.pad(&age)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::desktop_entry::test::entity;
+    use crate::db::desktop_entry::{self, DesktopEntryEntity};
+    use crate::db::migration::test::command;
+    use crate::db::prune::DAY;
+    use crate::db::query::{self, QueryEntity};
+    use crate::db::stats::{parse_age, write_table, EntryStats, StatsFilter};
+    use crate::db::Database;
+    use speculoos::prelude::*;
+    use std::borrow::Cow;
+
+    fn database(now: u64) -> Database {
+        let db = Database::in_memory().unwrap();
+        db.insert("terminal", &command("htop", 9, now - 40 * DAY))
+            .unwrap();
+        db.insert("terminal", &command("btop", 4, now - DAY))
+            .unwrap();
+        db.insert("terminal", &command("top", 1, 0)).unwrap();
+        db.insert(
+            query::COLLECTION,
+            &QueryEntity {
+                query: Cow::Borrowed("cargo build"),
+                weight: 2,
+                last_used: now,
+            },
+        )
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn should_count_launches_per_collection() {
+        let now = 100 * DAY;
+        let db = database(now);
+        let filter = StatsFilter {
+            collections: vec!["terminal".to_string()],
+            limit: 2,
+            ..Default::default()
+        };
+
+        let stats = db.stats(&filter, now).unwrap();
+
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].entries, 3);
+        assert_eq!(stats[0].launches, 17);
+        assert_eq!(
+            stats[0].top,
+            [
+                EntryStats {
+                    name: "htop".to_string(),
+                    launches: 10,
+                    last_used: Some(now - 40 * DAY),
+                },
+                EntryStats {
+                    name: "btop".to_string(),
+                    launches: 5,
+                    last_used: Some(now - DAY),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_filter_by_time_range() {
+        let now = 100 * DAY;
+        let db = database(now);
+        let filter = StatsFilter {
+            since: Some(7 * DAY),
+            limit: 10,
+            ..Default::default()
+        };
+
+        let stats = db.stats(&filter, now).unwrap();
+        let names: Vec<Vec<&str>> = stats
+            .iter()
+            .map(|stats| stats.top.iter().map(|entry| entry.name.as_str()).collect())
+            .collect();
+
+        assert_eq!(names, [vec!["cargo build"], vec!["btop"]]);
+
+        let mut table = vec![];
+        write_table(&stats, now, &mut table).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        asserting!("Should print the last usage age")
+            .that(&table.contains("  1d ago  btop"))
+            .is_true();
+    }
+
+    #[test]
+    fn should_list_desktop_entries_by_id() {
+        let db = Database::in_memory().unwrap();
+        for id in ["firefox.desktop", "org.mozilla.firefox.desktop"] {
+            let firefox = DesktopEntryEntity {
+                name: Cow::Borrowed("Firefox"),
+                ..entity(id, 1)
+            };
+            db.insert(desktop_entry::COLLECTION, &firefox).unwrap();
+        }
+
+        let filter = StatsFilter {
+            limit: 10,
+            ..Default::default()
+        };
+
+        let stats = db.stats(&filter, DAY).unwrap();
+        let names: Vec<_> = stats[0].top.iter().map(|entry| &entry.name).collect();
+        assert_eq!(names, ["firefox.desktop", "org.mozilla.firefox.desktop"]);
+    }
+
+    #[test]
+    fn should_parse_age() {
+        assert_eq!(parse_age("7d"), Ok(7 * DAY));
+        assert_eq!(parse_age("2w"), Ok(14 * DAY));
+        assert_eq!(parse_age("30m"), Ok(30 * 60));
+
+        asserting!("Should reject unknown units")
+            .that(&parse_age("3y"))
+            .is_err();
+
+        asserting!("Should require a unit")
+            .that(&parse_age("12"))
+            .is_err();
+
+        asserting!("Should reject overflowing durations")
+            .that(&parse_age("18446744073709551615w"))
+            .is_err();
+    }
+}
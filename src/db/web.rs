@@ -1,4 +1,7 @@
-use crate::db::{Database, Entity};
+use crate::app::mode::WEB_CONFIG;
+use crate::db::{timestamp, Database, Entity};
+use crate::SETTINGS;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use tracing::debug;
@@ -8,6 +11,9 @@ pub struct WebEntity<'a> {
     pub query: Cow<'a, str>,
     pub kind: Cow<'a, str>,
     pub weight: u8,
+    // Seconds since the unix epoch
+    #[serde(default)]
+    pub last_used: u64,
 }
 
 impl WebEntity<'_> {
@@ -22,16 +28,33 @@ impl WebEntity<'_> {
             kind: Cow::Borrowed(kind),
             query: Cow::Borrowed(query),
             weight,
+            last_used: timestamp(),
         };
 
         debug!("Inserting {entity:?} into '{kind}'");
-        db.insert(&entity.kind, &entity)
+        db.record(&entity.kind, &entity)
             .expect("Unable to insert terminal cache entry");
     }
 
     pub fn query(&self) -> String {
         format!("{}{}", self.kind, self.query)
     }
+
+    // The search url, as built by the pop-launcher web plugin unless defined in onagre config
+    pub fn url(&self) -> Option<String> {
+        if let Some(shortcut) = SETTINGS.web_shortcut(&self.kind) {
+            return Some(shortcut.url(&self.query));
+        }
+
+        let definition = WEB_CONFIG.get(&self.kind)?.first()?;
+        let query = utf8_percent_encode(self.query.trim(), NON_ALPHANUMERIC);
+        // Default definitions omit the scheme
+        if definition.query.contains("://") {
+            Some(format!("{}{query}", definition.query))
+        } else {
+            Some(format!("https://{}{query}", definition.query))
+        }
+    }
 }
 
 impl<'a> Entity<'a> for WebEntity<'a> {
@@ -42,4 +65,8 @@ impl<'a> Entity<'a> for WebEntity<'a> {
     fn get_weight(&self) -> u8 {
         self.weight
     }
+
+    fn get_last_used(&self) -> u64 {
+        self.last_used
+    }
 }
@@ -1,6 +1,7 @@
 use serde::Deserialize;
 use std::borrow::Cow;
-use std::path::Path;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
@@ -30,3 +31,153 @@ impl DesktopEntry<'_> {
             .map(|ini| ini.content)
     }
 }
+
+// The desktop file id: its path relative to the `applications` directory, with '/' replaced by '-'
+// See: https://specifications.freedesktop.org/desktop-entry-spec/latest/file-naming.html
+pub fn desktop_file_id(path: &Path) -> Option<String> {
+    let components: Vec<_> = path.components().collect();
+    let applications = components
+        .iter()
+        .rposition(|component| *component == Component::Normal("applications".as_ref()));
+
+    match applications {
+        Some(idx) if idx + 1 < components.len() => Some(
+            components[idx + 1..]
+                .iter()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("-"),
+        ),
+        _ => path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string()),
+    }
+}
+
+// Where a desktop file was installed from, an application can be installed several times
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DesktopEntryOrigin {
+    Flatpak,
+    Snap,
+    User,
+    System,
+}
+
+impl DesktopEntryOrigin {
+    pub fn from_path(path: &Path) -> Self {
+        let is_under = |dir: &str| {
+            path.components()
+                .any(|component| component == Component::Normal(dir.as_ref()))
+        };
+
+        if is_under("flatpak") {
+            DesktopEntryOrigin::Flatpak
+        } else if is_under("snapd") {
+            DesktopEntryOrigin::Snap
+        } else if dirs::data_dir().is_some_and(|dir| path.starts_with(dir)) {
+            DesktopEntryOrigin::User
+        } else {
+            DesktopEntryOrigin::System
+        }
+    }
+}
+
+impl fmt::Display for DesktopEntryOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let origin = match self {
+            DesktopEntryOrigin::Flatpak => "flatpak",
+            DesktopEntryOrigin::Snap => "snap",
+            DesktopEntryOrigin::User => "user",
+            DesktopEntryOrigin::System => "system",
+        };
+
+        write!(f, "{origin}")
+    }
+}
+
+// $XDG_DATA_HOME followed by $XDG_DATA_DIRS, by order of precedence
+pub fn data_dirs() -> Vec<PathBuf> {
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    dirs::data_dir()
+        .into_iter()
+        .chain(std::env::split_paths(&data_dirs))
+        .collect()
+}
+
+// Directories containing desktop files, by order of precedence
+pub fn application_dirs() -> Vec<PathBuf> {
+    data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+// Resolve a desktop file id to the desktop file path
+pub fn find_desktop_file(id: &str) -> Option<PathBuf> {
+    // `kde-foo.desktop` can either be `kde-foo.desktop` or `kde/foo.desktop`
+    let candidates: Vec<PathBuf> = std::iter::once(PathBuf::from(id))
+        .chain(
+            id.match_indices('-')
+                .map(|(idx, _)| PathBuf::from(&id[..idx]).join(&id[idx + 1..])),
+        )
+        .collect();
+
+    application_dirs().iter().find_map(|dir| {
+        candidates
+            .iter()
+            .map(|candidate| dir.join(candidate))
+            .find(|path| path.is_file())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::freedesktop::desktop::{desktop_file_id, DesktopEntryOrigin};
+    use std::path::Path;
+
+    #[test]
+    fn should_get_desktop_file_id() {
+        let id = desktop_file_id(Path::new("/usr/share/applications/firefox.desktop"));
+
+        assert_eq!(id.as_deref(), Some("firefox.desktop"));
+    }
+
+    #[test]
+    fn should_get_desktop_file_id_from_sub_directory() {
+        let id = desktop_file_id(Path::new(
+            "/usr/share/applications/kde/org.kde.dolphin.desktop",
+        ));
+
+        assert_eq!(id.as_deref(), Some("kde-org.kde.dolphin.desktop"));
+    }
+
+    #[test]
+    fn should_get_desktop_entry_origin() {
+        let origin = |path: &str| DesktopEntryOrigin::from_path(Path::new(path));
+
+        assert_eq!(
+            origin("/var/lib/flatpak/exports/share/applications/org.mozilla.firefox.desktop"),
+            DesktopEntryOrigin::Flatpak
+        );
+        assert_eq!(
+            origin("/var/lib/snapd/desktop/applications/firefox_firefox.desktop"),
+            DesktopEntryOrigin::Snap
+        );
+        assert_eq!(
+            origin("/usr/share/applications/firefox.desktop"),
+            DesktopEntryOrigin::System
+        );
+
+        let user = dirs::data_dir()
+            .unwrap()
+            .join("applications/firefox.desktop");
+        assert_eq!(
+            DesktopEntryOrigin::from_path(&user),
+            DesktopEntryOrigin::User
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use once_cell::sync::Lazy;
+use tracing::{debug, info};
+
+use crate::freedesktop::desktop::data_dirs;
+
+// The fallback theme every icon theme implicitly inherits from
+pub const HICOLOR: &str = "hicolor";
+
+// Detected once, this might spawn `gsettings` or `xfconf-query`
+pub static SYSTEM_ICON_THEME: Lazy<Option<String>> = Lazy::new(|| {
+    let theme = system_theme();
+    info!("System icon theme: {theme:?}");
+    theme
+});
+
+// Base directories of icon themes, by order of precedence
+// See: https://specifications.freedesktop.org/icon-theme-spec/latest/#directory_layout
+pub fn icon_dirs() -> Vec<PathBuf> {
+    dirs::home_dir()
+        .map(|home| home.join(".icons"))
+        .into_iter()
+        .chain(data_dirs().into_iter().map(|dir| dir.join("icons")))
+        .collect()
+}
+
+// Unthemed icons, looked up after every theme
+pub fn pixmaps_dirs() -> Vec<PathBuf> {
+    data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("pixmaps"))
+        .collect()
+}
+
+// The themes to look icons up in: each theme followed by its parents (depth first),
+// always ending with hicolor
+pub fn theme_chain(themes: &[String], icon_dirs: &[PathBuf]) -> Vec<String> {
+    let mut chain = vec![];
+    for theme in themes {
+        push_theme(theme, icon_dirs, &mut chain);
+    }
+
+    chain.retain(|theme| theme != HICOLOR);
+    chain.push(HICOLOR.to_string());
+    chain
+}
+
+fn push_theme(theme: &str, icon_dirs: &[PathBuf], chain: &mut Vec<String>) {
+    // Also protects against inheritance cycles
+    if chain.iter().any(|known| known == theme) {
+        return;
+    }
+
+    chain.push(theme.to_string());
+    for parent in inherited_themes(theme, icon_dirs) {
+        push_theme(&parent, icon_dirs, chain);
+    }
+}
+
+// The `Inherits` key from the first `index.theme` found for this theme
+fn inherited_themes(theme: &str, icon_dirs: &[PathBuf]) -> Vec<String> {
+    icon_dirs
+        .iter()
+        .find_map(|dir| std::fs::read_to_string(dir.join(theme).join("index.theme")).ok())
+        .and_then(|index| ini_value(&index, "Icon Theme", "Inherits"))
+        .map(|inherits| {
+            inherits
+                .split(',')
+                .map(str::trim)
+                .filter(|parent| !parent.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// The icon theme configured by the current desktop, falling back to GTK settings
+pub fn system_theme() -> Option<String> {
+    let config = dirs::config_dir()?;
+    let desktops = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+
+    desktops
+        .split(':')
+        .find_map(|desktop| desktop_theme(desktop, &config))
+        .or_else(|| gtk_theme(&config))
+}
+
+fn desktop_theme(desktop: &str, config: &Path) -> Option<String> {
+    match desktop.to_ascii_uppercase().as_str() {
+        "KDE" => {
+            let theme = std::fs::read_to_string(config.join("kdeglobals"))
+                .ok()
+                .and_then(|kdeglobals| ini_value(&kdeglobals, "Icons", "Theme"));
+            Some(theme.unwrap_or_else(|| "breeze".to_string()))
+        }
+        "COSMIC" => {
+            std::fs::read_to_string(config.join("cosmic/com.system76.CosmicTk/v1/icon_theme"))
+                .ok()
+                .and_then(|theme| unquote(&theme))
+        }
+        "GNOME" | "UNITY" | "BUDGIE" | "PANTHEON" => command_output(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "icon-theme"],
+        ),
+        "X-CINNAMON" => command_output(
+            "gsettings",
+            &["get", "org.cinnamon.desktop.interface", "icon-theme"],
+        ),
+        "MATE" => command_output("gsettings", &["get", "org.mate.interface", "icon-theme"]),
+        "XFCE" => command_output(
+            "xfconf-query",
+            &["--channel", "xsettings", "--property", "/Net/IconThemeName"],
+        ),
+        _ => None,
+    }
+}
+
+fn gtk_theme(config: &Path) -> Option<String> {
+    ["gtk-4.0", "gtk-3.0"]
+        .iter()
+        .filter_map(|gtk| std::fs::read_to_string(config.join(gtk).join("settings.ini")).ok())
+        .find_map(|settings| ini_value(&settings, "Settings", "gtk-icon-theme-name"))
+        .or_else(|| {
+            let gtkrc = std::fs::read_to_string(dirs::home_dir()?.join(".gtkrc-2.0")).ok()?;
+            gtkrc_value(&gtkrc, "gtk-icon-theme-name")
+        })
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => unquote(&String::from_utf8_lossy(&output.stdout)),
+        Ok(output) => {
+            debug!("{program} {args:?} failed: {output:?}");
+            None
+        }
+        Err(err) => {
+            debug!("Unable to run {program}: {err}");
+            None
+        }
+    }
+}
+
+// A key from an ini file section, e.g: index.theme, settings.ini or kdeglobals
+fn ini_value(content: &str, section: &str, key: &str) -> Option<String> {
+    let mut current_section = None;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            current_section = Some(name);
+        } else if current_section == Some(section) {
+            match line.split_once('=') {
+                Some((name, value)) if name.trim() == key => return unquote(value),
+                _ => {}
+            }
+        }
+    }
+
+    None
+}
+
+// Gtk2 rc files have no sections, e.g: gtk-icon-theme-name="Adwaita"
+fn gtkrc_value(content: &str, key: &str) -> Option<String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(name, _)| name.trim() == key)
+        .and_then(|(_, value)| unquote(value))
+}
+
+// Settings values might be quoted, e.g: 'Adwaita' from gsettings
+fn unquote(value: &str) -> Option<String> {
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'').trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::freedesktop::icon_theme::{gtkrc_value, ini_value, theme_chain};
+    use speculoos::prelude::*;
+    use std::path::{Path, PathBuf};
+
+    fn fixtures() -> Vec<PathBuf> {
+        vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("src/freedesktop/fixtures/icons")]
+    }
+
+    #[test]
+    fn should_read_icon_theme_from_settings() {
+        let settings = "[Settings]\n# comment\ngtk-theme-name=Adwaita-dark\ngtk-icon-theme-name = Papirus-Dark\n";
+        let kdeglobals = "[General]\nTheme=wrong\n\n[Icons]\nTheme=breeze-dark\n";
+
+        asserting!("Should read gtk settings.ini")
+            .that(&ini_value(settings, "Settings", "gtk-icon-theme-name"))
+            .is_some()
+            .is_equal_to("Papirus-Dark".to_string());
+
+        asserting!("Should read the key from the requested section")
+            .that(&ini_value(kdeglobals, "Icons", "Theme"))
+            .is_some()
+            .is_equal_to("breeze-dark".to_string());
+
+        asserting!("Should read gtkrc-2.0")
+            .that(&gtkrc_value(
+                "gtk-icon-theme-name=\"Arc\"\n",
+                "gtk-icon-theme-name",
+            ))
+            .is_some()
+            .is_equal_to("Arc".to_string());
+    }
+
+    #[test]
+    fn should_follow_inherited_themes() {
+        let chain = theme_chain(&["Custom".to_string(), "Other".to_string()], &fixtures());
+
+        assert_eq!(chain, ["Custom", "Parent", "Adwaita", "Other", "hicolor"]);
+    }
+
+    #[test]
+    fn should_end_with_hicolor_on_inheritance_cycle() {
+        let chain = theme_chain(&["Cycle".to_string()], &fixtures());
+
+        assert_eq!(chain, ["Cycle", "Loop", "Parent", "Adwaita", "hicolor"]);
+    }
+}
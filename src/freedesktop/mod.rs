@@ -1 +1,2 @@
 pub mod desktop;
+pub mod icon_theme;
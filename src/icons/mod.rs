@@ -1,23 +1,55 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
 use crate::config::color::OnagreColor;
+use crate::freedesktop::icon_theme::{self, SYSTEM_ICON_THEME};
 use crate::THEME;
 use anyhow::anyhow;
+use iced::widget::image;
 use iced::widget::Svg;
 use iced::Renderer;
 use iced_core::svg::Handle;
 use onagre_launcher_toolkit::launcher::IconSource;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
-// We use this only for symbolic svg icons which needs to be loaded with a color theme
-// For other icons, the freedesktop-icon crate has a cache already
-pub(crate) static SYMBOLIC_ICON_CACHE: Lazy<Mutex<HashMap<String, Vec<u8>>>> =
+pub(crate) mod raster;
+pub(crate) mod symbolic;
+pub(crate) mod xpm;
+
+// Svg handles are built once, symbolic icons are keyed by color and path, other icons by path.
+// Cloning a handle is cheap and lets iced reuse its rasterization cache.
+pub(crate) static SVG_HANDLE_CACHE: Lazy<Mutex<HashMap<String, Handle>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Icon theme lookups keyed by themes, size and name, `None` when the icon does not exist
+static ICON_LOOKUP_CACHE: Lazy<Mutex<HashMap<String, Option<IconPath>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Configured themes followed by the system one, their parents and hicolor
+static THEME_CHAIN_CACHE: Lazy<Mutex<HashMap<Vec<String>, Vec<String>>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+fn theme_chain(themes: &[String]) -> Vec<String> {
+    THEME_CHAIN_CACHE
+        .lock()
+        .unwrap()
+        .entry(themes.to_vec())
+        .or_insert_with(|| {
+            let themes: Vec<String> = themes
+                .iter()
+                .cloned()
+                .chain(SYSTEM_ICON_THEME.clone())
+                .collect();
+            icon_theme::theme_chain(&themes, &icon_theme::icon_dirs())
+        })
+        .clone()
+}
+
 // This work is licenced under the terms of either the GNU LGPL v3 or
 // Creative Commons Attribution-Share Alike 3.0 United States License.
 //
@@ -34,34 +66,35 @@ const FALLBACK_ICON_PATH: &str = "dialog-question-symbolic.svg";
 // and cache the result.
 pub fn fallback_icon(color: &OnagreColor) -> Svg<Renderer> {
     let hex_color = color.to_string();
-    let mut cache = SYMBOLIC_ICON_CACHE.lock().unwrap();
     let path = Path::new(FALLBACK_ICON_PATH);
     let key = format!("{hex_color}{path:?}");
 
-    let svg = match cache.get(&key) {
-        Some(svg) => svg,
-        None => {
-            let svg = inject_color_into_svg(FALLBACK_ICON, &hex_color);
-            cache.insert(key.clone(), svg.into_bytes());
-            cache.get(&key).unwrap()
-        }
-    };
-
-    let handle = Handle::from_memory(svg.clone());
+    let handle = SVG_HANDLE_CACHE
+        .lock()
+        .unwrap()
+        .entry(key)
+        .or_insert_with(|| {
+            let svg = symbolic::recolor(FALLBACK_ICON, color)
+                .unwrap_or_else(|_| FALLBACK_ICON.to_string());
+            Handle::from_memory(svg.into_bytes())
+        })
+        .clone();
 
     Svg::new(handle)
 }
 
-fn inject_color_into_svg(content: &str, hex_color: &str) -> String {
-    // Svg does not support transparency for hex colors
-    let hex_color = &hex_color[0..7];
+// Resolve icons off the UI thread, rows display the fallback icon until then
+pub async fn resolve_names(names: Vec<String>, themes: Vec<String>) {
+    let resolved = tokio::task::spawn_blocking(move || {
+        for name in &names {
+            IconPath::lookup(name, &themes, THEME.icon_size);
+        }
+    })
+    .await;
 
-    let mut string = content.to_string();
-    for (pos, _) in content.match_indices('#') {
-        string.replace_range(pos..pos + 7, hex_color);
+    if let Err(err) = resolved {
+        warn!("Icon resolution failed: {err}");
     }
-
-    string
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -78,7 +111,34 @@ impl AsRef<IconPath> for IconPath {
 }
 
 impl IconPath {
-    pub fn lookup(name: &str, theme: &str, size: u16) -> Option<Self> {
+    pub fn lookup(name: &str, themes: &[String], size: u16) -> Option<Self> {
+        let key = format!("{}:{size}:{name}", themes.join(","));
+        if let Some(icon) = ICON_LOOKUP_CACHE.lock().unwrap().get(&key) {
+            return icon.clone();
+        }
+
+        let icon = Self::find(name, themes, size);
+        ICON_LOOKUP_CACHE.lock().unwrap().insert(key, icon.clone());
+        icon
+    }
+
+    // Non blocking lookup, `None` until the icon was resolved with `lookup`
+    pub fn lookup_cached(name: &str, themes: &[String], size: u16) -> Option<Option<Self>> {
+        let key = format!("{}:{size}:{name}", themes.join(","));
+        ICON_LOOKUP_CACHE.lock().unwrap().get(&key).cloned()
+    }
+
+    // Try each theme of the chain, then the pixmaps directories
+    fn find(name: &str, themes: &[String], size: u16) -> Option<Self> {
+        theme_chain(themes)
+            .iter()
+            .find_map(|theme| Self::find_in_theme(name, theme, size))
+            .or_else(|| Self::find_in_pixmaps(name))
+    }
+
+    // `size` is a scaled size, icon themes provide dedicated icons for HiDPI screens
+    // and expect the unscaled size along with an integer scale
+    fn find_in_theme(name: &str, theme: &str, size: u16) -> Option<Self> {
         if name.ends_with("-symbolic") {
             freedesktop_icons::lookup(name)
                 .with_theme(theme)
@@ -87,15 +147,39 @@ impl IconPath {
                 .find()
                 .and_then(|icon| Self::from_path(icon, true))
         } else {
+            let scale = THEME.scale.ceil().max(1.0);
             freedesktop_icons::lookup(name)
-                .with_scale(1)
+                .with_scale(scale as u16)
                 .with_theme(theme)
-                .with_size(size)
+                .with_size((size as f32 / scale).round() as u16)
                 .find()
                 .and_then(|icon| Self::from_path(icon, false))
         }
     }
 
+    fn find_in_pixmaps(name: &str) -> Option<Self> {
+        icon_theme::pixmaps_dirs().iter().find_map(|dir| {
+            ["svg", "png", "xpm"]
+                .iter()
+                .map(|extension| dir.join(format!("{name}.{extension}")))
+                .find(|path| path.is_file())
+                .and_then(|path| Self::from_path(path, name.ends_with("-symbolic")))
+        })
+    }
+
+    // Icons defined in onagre config are either an icon name or an absolute path
+    pub fn from_name_or_path(icon: &str, themes: &[String]) -> Option<Self> {
+        if icon.starts_with('/') {
+            let symbolic = Path::new(icon)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().ends_with("-symbolic"))
+                .unwrap_or(false);
+            IconPath::from_path(icon, symbolic)
+        } else {
+            IconPath::lookup(icon, themes, THEME.icon_size)
+        }
+    }
+
     pub fn absolute_from_icon_source(source: Option<&IconSource>) -> Option<Self> {
         source.and_then(|icon| {
             let (path, symbolic) = match icon {
@@ -107,14 +191,13 @@ impl IconPath {
         })
     }
 
-    pub fn from_source(source: &IconSource, theme: &str) -> Option<Self> {
-        match source {
-            IconSource::Name(name) => IconPath::lookup(name, theme, THEME.icon_size),
-            IconSource::Mime(mime) => {
-                let name = mime.replace('/', "-");
-                IconPath::lookup(&name, theme, THEME.icon_size)
-            }
-        }
+    pub fn from_source(source: &IconSource, themes: &[String]) -> Option<Self> {
+        IconPath::lookup(&source_name(source), themes, THEME.icon_size)
+    }
+
+    // See `lookup_cached`
+    pub fn from_source_cached(source: &IconSource, themes: &[String]) -> Option<Option<Self>> {
+        IconPath::lookup_cached(&source_name(source), themes, THEME.icon_size)
     }
 
     pub fn from_path<P: AsRef<Path>>(path: P, symbolic: bool) -> Option<Self> {
@@ -129,28 +212,54 @@ impl IconPath {
             })
     }
 
+    // Svg icons are not images, see `to_svg`
+    pub fn to_image_handle(&self) -> Option<image::Handle> {
+        match self.extension {
+            Extension::Svg => None,
+            // Decoded by iced
+            Extension::Png | Extension::Jpeg | Extension::Webp => {
+                Some(image::Handle::from_path(&self.path))
+            }
+            Extension::Ico => raster::ico_handle(&self.path),
+            Extension::Xpm => raster::xpm_handle(&self.path),
+        }
+    }
+
     // If we have a symbolic icon try to replace the foreground color with the current
     // one and cache the result, otherwise build the svg from icon path
     pub fn to_svg(&self, color: &OnagreColor) -> Svg<Renderer> {
-        if self.symbolic {
-            let mut icon_cache = SYMBOLIC_ICON_CACHE.lock().unwrap();
+        let mut icon_cache = SVG_HANDLE_CACHE.lock().unwrap();
+        let handle = if self.symbolic {
             let hex_color = color.to_string();
             let key = format!("{hex_color}{:?}", self.path);
-            let svg = match icon_cache.get(&key) {
-                None => {
-                    let content =
-                        std::fs::read_to_string(&self.path).expect("Icon path does not exists");
-                    let svg = inject_color_into_svg(&content, &hex_color);
-                    icon_cache.insert(key.clone(), svg.as_bytes().to_vec());
-                    icon_cache.get(&key).unwrap()
+            icon_cache.entry(key).or_insert_with(|| {
+                // Unreadable or malformed icons are displayed as is
+                let svg = std::fs::read_to_string(&self.path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|content| symbolic::recolor(&content, color));
+                match svg {
+                    Ok(svg) => Handle::from_memory(svg.into_bytes()),
+                    Err(err) => {
+                        warn!("Failed to recolor symbolic icon {:?}: {err}", self.path);
+                        Handle::from_path(&self.path)
+                    }
                 }
-                Some(svg) => svg,
-            };
-            let handle = Handle::from_memory(svg.clone());
-            Svg::new(handle)
+            })
         } else {
-            Svg::from_path(&self.path)
-        }
+            icon_cache
+                .entry(format!("{:?}", self.path))
+                .or_insert_with(|| Handle::from_path(&self.path))
+        };
+
+        Svg::new(handle.clone())
+    }
+}
+
+// Mime types are looked up as icon names, e.g: "text-plain"
+pub(crate) fn source_name(source: &IconSource) -> Cow<'_, str> {
+    match source {
+        IconSource::Name(name) => Cow::Borrowed(name.as_ref()),
+        IconSource::Mime(mime) => Cow::Owned(mime.replace('/', "-")),
     }
 }
 
@@ -158,6 +267,10 @@ impl IconPath {
 pub enum Extension {
     Svg,
     Png,
+    Jpeg,
+    Webp,
+    Ico,
+    Xpm,
 }
 
 impl TryFrom<&str> for Extension {
@@ -167,7 +280,75 @@ impl TryFrom<&str> for Extension {
         match value {
             "png" => Ok(Extension::Png),
             "svg" => Ok(Extension::Svg),
+            "jpg" | "jpeg" => Ok(Extension::Jpeg),
+            "webp" => Ok(Extension::Webp),
+            "ico" => Ok(Extension::Ico),
+            "xpm" => Ok(Extension::Xpm),
             ext => Err(anyhow!("Unsupported icon extension: {ext}")),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::icons::{Extension, IconPath};
+    use speculoos::prelude::*;
+    use std::path::Path;
+
+    fn fixture(name: &str) -> IconPath {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/icons/fixtures")
+            .join(name);
+        IconPath::from_path(path, false).unwrap()
+    }
+
+    #[test]
+    fn should_render_raster_fixtures_as_images() {
+        for (name, extension, size, pixel) in [
+            ("checker.xpm", Extension::Xpm, (4, 2), [128, 128, 128, 255]),
+            ("checker.ico", Extension::Ico, (2, 2), [0, 0, 0, 0]),
+            ("white.jpg", Extension::Jpeg, (4, 2), [255, 255, 255, 255]),
+            ("pixel.webp", Extension::Webp, (1, 1), [0, 0, 0, 0]),
+        ] {
+            let icon = fixture(name);
+            assert_eq!(icon.extension, extension);
+
+            // Decoded the way iced renders it
+            let handle = icon.to_image_handle().unwrap();
+            let image = iced_graphics::image::load(&handle).unwrap().to_rgba8();
+            let (width, height) = image.dimensions();
+            assert_eq!((width, height), size, "{}", name);
+            // Bottom right pixel
+            assert_eq!(image.get_pixel(width - 1, height - 1).0, pixel, "{}", name);
+        }
+    }
+
+    #[test]
+    fn should_detect_raster_extensions() {
+        for (path, extension) in [
+            ("/usr/share/pixmaps/app.jpg", Extension::Jpeg),
+            ("/usr/share/pixmaps/app.jpeg", Extension::Jpeg),
+            ("/usr/share/pixmaps/app.webp", Extension::Webp),
+            ("/usr/share/pixmaps/app.png", Extension::Png),
+        ] {
+            let icon = IconPath::from_path(path, false).map(|icon| icon.extension);
+            assert_eq!(icon, Some(extension));
+        }
+    }
+
+    #[test]
+    fn should_cache_missing_icon_lookup() {
+        let name = "onagre-test-missing-icon";
+        let themes = vec!["hicolor".to_string()];
+        asserting!("Should not be resolved yet")
+            .that(&IconPath::lookup_cached(name, &themes, 24))
+            .is_none();
+
+        IconPath::lookup(name, &themes, 24);
+
+        asserting!("Should remember the icon does not exist")
+            .that(&IconPath::lookup_cached(name, &themes, 24))
+            .is_some()
+            .is_none();
+    }
+}
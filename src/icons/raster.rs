@@ -0,0 +1,182 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use crate::icons::xpm;
+use anyhow::anyhow;
+use iced::widget::image::Handle;
+use ico::{IconDir, IconImage};
+use once_cell::sync::Lazy;
+use tracing::{debug, warn};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Decoded icons, `None` when the icon could not be decoded so we don't try again
+static RASTER_ICON_CACHE: Lazy<Mutex<HashMap<PathBuf, Option<Handle>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Load a `.ico` file as an image handle. The decoded image is written as png in onagre
+// cache directory, keyed by the source modification time, so it's only decoded once.
+// Malformed icons are logged and ignored.
+pub fn ico_handle(path: &Path) -> Option<Handle> {
+    cached_handle(path, load_ico)
+}
+
+// Xpm icons are cheap to decode, they are only cached in memory
+pub fn xpm_handle(path: &Path) -> Option<Handle> {
+    cached_handle(path, |path| {
+        let image = xpm::decode(&std::fs::read_to_string(path)?)?;
+        Ok(Handle::from_pixels(image.width, image.height, image.rgba))
+    })
+}
+
+fn cached_handle<F>(path: &Path, load: F) -> Option<Handle>
+where
+    F: FnOnce(&Path) -> anyhow::Result<Handle>,
+{
+    let mut cache = RASTER_ICON_CACHE.lock().unwrap();
+    cache
+        .entry(path.to_path_buf())
+        .or_insert_with(|| match load(path) {
+            Ok(handle) => Some(handle),
+            Err(err) => {
+                warn!("Failed to decode icon {path:?}: {err}");
+                None
+            }
+        })
+        .clone()
+}
+
+fn load_ico(path: &Path) -> anyhow::Result<Handle> {
+    let cached = cached_png_path(path);
+    if let Some(cached) = cached.as_ref().filter(|cached| cached.exists()) {
+        return Ok(Handle::from_path(cached));
+    }
+
+    let bytes = std::fs::read(path)?;
+    let png = ico_to_png(&bytes)?;
+
+    // Path handles are cheaper to clone on every frame than in memory ones
+    if let Some(cached) = cached {
+        match write_cache(&cached, &png) {
+            Ok(()) => return Ok(Handle::from_path(cached)),
+            Err(err) => debug!("Unable to cache decoded icon {cached:?}: {err}"),
+        }
+    }
+
+    Ok(Handle::from_memory(png))
+}
+
+// Favicons are sometimes plain png files with an `.ico` extension
+fn ico_to_png(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        return Ok(bytes.to_vec());
+    }
+
+    let image = decode_ico(bytes)?;
+    let mut png = vec![];
+    image.write_png(&mut png)?;
+    Ok(png)
+}
+
+// Decode the largest image of an icon directory
+fn decode_ico(bytes: &[u8]) -> anyhow::Result<IconImage> {
+    let icon = IconDir::read(Cursor::new(bytes))?;
+    let entry = icon
+        .entries()
+        .iter()
+        .max_by_key(|entry| entry.width() * entry.height())
+        .ok_or_else(|| anyhow!("empty icon directory"))?;
+
+    Ok(entry.decode()?)
+}
+
+fn cached_png_path(path: &Path) -> Option<PathBuf> {
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    let key = hasher.finish();
+
+    dirs::cache_dir().map(|cache| {
+        cache
+            .join("onagre")
+            .join("icons")
+            .join(format!("{key:x}-{modified}.png"))
+    })
+}
+
+fn write_cache(path: &Path, png: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, png)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::icons::raster::{ico_to_png, PNG_SIGNATURE};
+    use ico::{IconDir, IconDirEntry, IconImage, ResourceType};
+    use speculoos::prelude::*;
+
+    fn ico_bytes(sizes: &[u32]) -> Vec<u8> {
+        let mut icon = IconDir::new(ResourceType::Icon);
+        for size in sizes {
+            let rgba = vec![255; (size * size * 4) as usize];
+            let image = IconImage::from_rgba_data(*size, *size, rgba);
+            icon.add_entry(IconDirEntry::encode(&image).unwrap());
+        }
+
+        let mut bytes = vec![];
+        icon.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn should_decode_largest_ico_entry_to_png() {
+        let png = ico_to_png(&ico_bytes(&[16, 32])).unwrap();
+        let image = IconImage::read_png(png.as_slice()).unwrap();
+
+        assert_eq!((image.width(), image.height()), (32, 32));
+    }
+
+    #[test]
+    fn should_decode_ico_fixture() {
+        let png = ico_to_png(include_bytes!("fixtures/checker.ico")).unwrap();
+        let image = IconImage::read_png(png.as_slice()).unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.rgba_data()[..4], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn should_keep_png_with_ico_extension() {
+        let png = ico_to_png(&[PNG_SIGNATURE, b"data"].concat());
+
+        asserting!("Should return png content as is")
+            .that(&png)
+            .is_ok()
+            .is_equal_to([PNG_SIGNATURE, b"data"].concat());
+    }
+
+    #[test]
+    fn should_not_panic_on_malformed_ico() {
+        asserting!("Should fail to decode garbage")
+            .that(&ico_to_png(b"not an icon"))
+            .is_err();
+
+        asserting!("Should fail to decode an empty icon directory")
+            .that(&ico_to_png(&ico_bytes(&[])))
+            .is_err();
+    }
+}
@@ -0,0 +1,350 @@
+use std::ops::Range;
+
+use crate::config::color::OnagreColor;
+use anyhow::anyhow;
+use iced::Color;
+use roxmltree::{Document, Node};
+
+// Default colors GTK uses for the semantic classes of symbolic icons
+const SUCCESS_COLOR: &str = "#4e9a06";
+const WARNING_COLOR: &str = "#f57900";
+const ERROR_COLOR: &str = "#cc0000";
+
+const PAINT_PROPERTIES: [&str; 3] = ["fill", "stroke", "color"];
+
+// Paint values that are not colors and must be left untouched
+const KEPT_VALUES: [&str; 7] = [
+    "none",
+    "transparent",
+    "inherit",
+    "initial",
+    "unset",
+    "context-fill",
+    "context-stroke",
+];
+
+// Recolor a symbolic icon with the theme color, like GTK does:
+// fill, stroke and color values (including `currentColor`) are replaced by the theme
+// color, elements with a `success`, `warning` or `error` class get GTK semantic colors,
+// and the theme alpha is applied as the root opacity.
+// Only the colors are rewritten, ids, references and comments are left as is.
+pub fn recolor(content: &str, color: &OnagreColor) -> anyhow::Result<String> {
+    let color = Color::from(*color);
+    let foreground = hex(color);
+    let document = Document::parse(content)?;
+    let mut edits: Vec<(Range<usize>, String)> = vec![];
+
+    for node in document.descendants().filter(Node::is_element) {
+        let paint = node
+            .ancestors()
+            .filter_map(|node| node.attribute("class"))
+            .find_map(semantic_color)
+            .unwrap_or(&foreground);
+
+        for attribute in node.attributes() {
+            if attribute.namespace().is_some() {
+                continue;
+            }
+
+            let name = attribute.name();
+            if PAINT_PROPERTIES.contains(&name) && is_color(attribute.value()) {
+                edits.push((
+                    value_range(content, attribute.position())?,
+                    paint.to_string(),
+                ));
+            } else if name == "style" {
+                let range = value_range(content, attribute.position())?;
+                let style = recolor_declarations(&content[range.clone()], paint);
+                edits.push((range, style));
+            }
+        }
+
+        if node.has_tag_name("style") {
+            let range = inner_range(content, node.range());
+            let stylesheet = recolor_stylesheet(&content[range.clone()], &foreground);
+            edits.push((range, stylesheet));
+        }
+    }
+
+    // Elements without paint inherit it from the root
+    let root = document.root_element();
+    let root_start = root.range().start;
+    let tag_end = content[root_start + 1..]
+        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .map(|end| root_start + 1 + end)
+        .ok_or_else(|| anyhow!("invalid svg root element"))?;
+
+    if root.attribute("fill").is_none() {
+        edits.push((tag_end..tag_end, format!(r#" fill="{foreground}""#)));
+    }
+
+    // Svg colors have no alpha channel
+    if color.a < 1.0 {
+        match root.attribute_node("opacity") {
+            Some(opacity) => {
+                let value = opacity.value().trim().parse::<f32>().unwrap_or(1.0);
+                let range = value_range(content, opacity.position())?;
+                edits.push((range, format!("{:.3}", value * color.a)));
+            }
+            None => edits.push((tag_end..tag_end, format!(r#" opacity="{:.3}""#, color.a))),
+        }
+    }
+
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut svg = content.to_string();
+    for (range, value) in edits.into_iter().rev() {
+        svg.replace_range(range, &value);
+    }
+
+    Ok(svg)
+}
+
+fn hex(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn is_color(value: &str) -> bool {
+    let value = value.trim();
+    !value.is_empty()
+        && !value.starts_with("url(")
+        && !KEPT_VALUES
+            .iter()
+            .any(|kept| value.eq_ignore_ascii_case(kept))
+}
+
+// Semantic classes from GTK, along with the KDE color scheme ones used by Papirus
+fn semantic_color(names: &str) -> Option<&'static str> {
+    names
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .find_map(|name| match name {
+            "success" | "ColorScheme-PositiveText" => Some(SUCCESS_COLOR),
+            "warning" | "ColorScheme-NeutralText" => Some(WARNING_COLOR),
+            "error" | "ColorScheme-NegativeText" => Some(ERROR_COLOR),
+            _ => None,
+        })
+}
+
+// Rewrite the paint declarations of an inline style, e.g: "fill:#2e3436;fill-opacity:0.35"
+fn recolor_declarations(style: &str, paint: &str) -> String {
+    style
+        .split(';')
+        .map(|declaration| match declaration.split_once(':') {
+            Some((name, value))
+                if PAINT_PROPERTIES.contains(&name.trim())
+                    && is_color(value.trim_end_matches("!important")) =>
+            {
+                let important = if value.contains("!important") {
+                    " !important"
+                } else {
+                    ""
+                };
+                format!("{name}:{paint}{important}")
+            }
+            _ => declaration.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+// Rewrite each rule of a `<style>` element, semantic class selectors keep their meaning
+fn recolor_stylesheet(stylesheet: &str, foreground: &str) -> String {
+    let mut recolored = String::with_capacity(stylesheet.len());
+    let mut rest = stylesheet;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+            break;
+        };
+
+        let selector = &rest[..open];
+        let paint = semantic_color(selector).unwrap_or(foreground);
+        recolored.push_str(&rest[..=open]);
+        recolored.push_str(&recolor_declarations(&rest[open + 1..close], paint));
+        recolored.push('}');
+        rest = &rest[close + 1..];
+    }
+
+    recolored.push_str(rest);
+    recolored
+}
+
+// The raw value of an attribute, positions point to the attribute name
+fn value_range(content: &str, name_start: usize) -> anyhow::Result<Range<usize>> {
+    let range = || {
+        let equal = name_start + content[name_start..].find('=')?;
+        let quote_start = equal + content[equal..].find(['"', '\''])?;
+        let quote = &content[quote_start..quote_start + 1];
+        let start = quote_start + 1;
+        let end = start + content[start..].find(quote)?;
+        Some(start..end)
+    };
+
+    range().ok_or_else(|| anyhow!("invalid attribute at {name_start}"))
+}
+
+// The raw content of an element, between its start and end tags
+fn inner_range(content: &str, element: Range<usize>) -> Range<usize> {
+    let raw = &content[element.clone()];
+    match (raw.find('>'), raw.rfind("</")) {
+        (Some(start), Some(end)) if start < end => element.start + start + 1..element.start + end,
+        // Self closing element
+        _ => element.end..element.end,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::color::OnagreColor;
+    use crate::icons::symbolic::recolor;
+    use roxmltree::Document;
+    use speculoos::prelude::*;
+
+    const CORPUS: [(&str, &str); 6] = [
+        (
+            "adwaita/dialog-question-symbolic.svg",
+            include_str!("fixtures/symbolic/adwaita/dialog-question-symbolic.svg"),
+        ),
+        (
+            "adwaita/edit-find-symbolic.svg",
+            include_str!("fixtures/symbolic/adwaita/edit-find-symbolic.svg"),
+        ),
+        (
+            "adwaita/network-wireless-signal-weak-symbolic.svg",
+            include_str!("fixtures/symbolic/adwaita/network-wireless-signal-weak-symbolic.svg"),
+        ),
+        (
+            "adwaita/battery-level-10-symbolic.svg",
+            include_str!("fixtures/symbolic/adwaita/battery-level-10-symbolic.svg"),
+        ),
+        (
+            "papirus/folder-symbolic.svg",
+            include_str!("fixtures/symbolic/papirus/folder-symbolic.svg"),
+        ),
+        (
+            "papirus/dialog-warning-symbolic.svg",
+            include_str!("fixtures/symbolic/papirus/dialog-warning-symbolic.svg"),
+        ),
+    ];
+
+    fn color(hex: &str) -> OnagreColor {
+        OnagreColor::from(hex).unwrap()
+    }
+
+    // The colors declared by the elements, either as attributes or style declarations
+    fn paints(svg: &str) -> Vec<(String, String)> {
+        let document = Document::parse(svg).unwrap();
+        let mut paints = vec![];
+        for node in document.descendants().filter(|node| node.is_element()) {
+            let id = node.attribute("id").unwrap_or(node.tag_name().name());
+            for property in ["fill", "stroke", "color"] {
+                if let Some(value) = node.attribute(property) {
+                    paints.push((id.to_string(), value.to_string()));
+                }
+            }
+
+            let declarations = node.attribute("style").unwrap_or("").split(';');
+            for (name, value) in declarations.filter_map(|decl| decl.split_once(':')) {
+                if ["fill", "stroke", "color"].contains(&name.trim()) {
+                    paints.push((id.to_string(), value.trim().to_string()));
+                }
+            }
+        }
+
+        paints
+    }
+
+    #[test]
+    fn should_recolor_symbolic_corpus() {
+        for (name, svg) in CORPUS {
+            let recolored = recolor(svg, &color("#abcdef")).unwrap();
+            let paints = paints(&recolored);
+
+            assert!(Document::parse(&recolored).is_ok(), "{}: invalid svg", name);
+
+            for (id, paint) in paints {
+                let expected = ["#abcdef", "none", "#4e9a06", "#f57900", "#cc0000"];
+                assert!(
+                    expected.contains(&paint.as_str()) || paint.starts_with("url("),
+                    "{}: unexpected paint {} on {}",
+                    name,
+                    paint,
+                    id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn should_keep_references_and_comments() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+            <!-- #2e3436 is the Adwaita foreground -->
+            <defs><path id="a" d="m 0 0 h 16" fill="#fff"/></defs>
+            <use xlink:href="#a"/>
+            <path d="m 0 0 h 8" fill="url(#gradient)" stroke="#222"/>
+        </svg>"##;
+
+        let recolored = recolor(svg, &color("#abcdef")).unwrap();
+
+        assert_eq!(
+            recolored,
+            r##"<svg fill="#abcdef" xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+            <!-- #2e3436 is the Adwaita foreground -->
+            <defs><path id="a" d="m 0 0 h 16" fill="#abcdef"/></defs>
+            <use xlink:href="#a"/>
+            <path d="m 0 0 h 8" fill="url(#gradient)" stroke="#abcdef"/>
+        </svg>"##
+        );
+    }
+
+    #[test]
+    fn should_recolor_current_color_and_styles() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" fill="none">
+            <style>.ColorScheme-Text { color:#dfdfdf; } .error { fill: red }</style>
+            <path class="ColorScheme-Text" style="fill:currentColor;fill-opacity:0.35"/>
+            <g class="warning"><path style="fill:#f00 !important;stroke:none"/></g>
+        </svg>"#;
+
+        let recolored = recolor(svg, &color("#abcdef")).unwrap();
+
+        assert_eq!(
+            recolored,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" fill="none">
+            <style>.ColorScheme-Text { color:#abcdef; } .error { fill:#cc0000}</style>
+            <path class="ColorScheme-Text" style="fill:#abcdef;fill-opacity:0.35"/>
+            <g class="warning"><path style="fill:#f57900 !important;stroke:none"/></g>
+        </svg>"#
+        );
+    }
+
+    #[test]
+    fn should_apply_alpha_as_opacity() {
+        let with_opacity = r#"<svg xmlns="http://www.w3.org/2000/svg" opacity="0.5"/>"#;
+        let without_opacity = r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#;
+
+        asserting!("Should multiply the root opacity")
+            .that(&recolor(with_opacity, &color("#ffffff80")))
+            .is_ok()
+            .is_equal_to(
+                r##"<svg fill="#ffffff" xmlns="http://www.w3.org/2000/svg" opacity="0.251"/>"##
+                    .to_string(),
+            );
+
+        asserting!("Should add a root opacity")
+            .that(&recolor(without_opacity, &color("#ffffff80")))
+            .is_ok()
+            .is_equal_to(
+                r##"<svg fill="#ffffff" opacity="0.502" xmlns="http://www.w3.org/2000/svg"/>"##
+                    .to_string(),
+            );
+    }
+
+    #[test]
+    fn should_fail_on_malformed_svg() {
+        asserting!("Should not recolor invalid xml")
+            .that(&recolor(
+                "<svg><path fill=\"#000\"></svg>",
+                &color("#ffffff"),
+            ))
+            .is_err();
+    }
+}
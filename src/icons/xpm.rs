@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use anyhow::{anyhow, bail};
+
+// Icons are small, this protects against allocating for bogus headers
+const MAX_SIZE: usize = 1024;
+// Enough for any palette an icon can use
+const MAX_CHARS_PER_PIXEL: usize = 8;
+
+// A decoded XPM3 image, still shipped by many legacy applications
+#[derive(Debug, Clone, PartialEq)]
+pub struct XpmImage {
+    pub width: u32,
+    pub height: u32,
+    // Row major RGBA pixels
+    pub rgba: Vec<u8>,
+}
+
+pub fn decode(content: &str) -> anyhow::Result<XpmImage> {
+    let mut strings = quoted_strings(content).into_iter();
+    let header = strings
+        .next()
+        .ok_or_else(|| anyhow!("missing xpm header"))?;
+    let values: Vec<usize> = header
+        .split_whitespace()
+        .take(4)
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+
+    let [width, height, colors, chars_per_pixel] = values[..] else {
+        bail!("invalid xpm header {header:?}");
+    };
+
+    if !(1..=MAX_CHARS_PER_PIXEL).contains(&chars_per_pixel)
+        || !(1..=MAX_SIZE).contains(&width)
+        || !(1..=MAX_SIZE).contains(&height)
+        // A palette larger than the image is bogus
+        || colors > width * height
+    {
+        bail!("invalid xpm header {header:?}");
+    }
+
+    let mut palette = HashMap::with_capacity(colors);
+    for _ in 0..colors {
+        let line = strings.next().ok_or_else(|| anyhow!("missing xpm color"))?;
+        let key = line
+            .get(..chars_per_pixel)
+            .ok_or_else(|| anyhow!("invalid xpm color {line:?}"))?;
+        palette.insert(key, parse_color_line(&line[chars_per_pixel..])?);
+    }
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for _ in 0..height {
+        let row = strings
+            .next()
+            .ok_or_else(|| anyhow!("missing xpm pixels"))?;
+        for x in 0..width {
+            let key = row
+                .get(x * chars_per_pixel..(x + 1) * chars_per_pixel)
+                .ok_or_else(|| anyhow!("xpm row is too short {row:?}"))?;
+            let color = palette
+                .get(key)
+                .ok_or_else(|| anyhow!("unknown xpm color {key:?}"))?;
+            rgba.extend_from_slice(color);
+        }
+    }
+
+    Ok(XpmImage {
+        width: u32::try_from(width)?,
+        height: u32::try_from(height)?,
+        rgba,
+    })
+}
+
+// Xpm files are C sources, the image is made of the quoted strings
+fn quoted_strings(content: &str) -> Vec<&str> {
+    let mut strings = vec![];
+    let mut rest = content;
+    loop {
+        let comment = rest.find("/*");
+        let quote = rest.find('"');
+        match (comment, quote) {
+            (Some(comment), Some(quote)) if comment < quote => match rest[comment..].find("*/") {
+                Some(end) => rest = &rest[comment + end + 2..],
+                None => break,
+            },
+            (_, Some(quote)) => {
+                let start = quote + 1;
+                match rest[start..].find('"') {
+                    Some(end) => {
+                        strings.push(&rest[start..start + end]);
+                        rest = &rest[start + end + 1..];
+                    }
+                    None => break,
+                }
+            }
+            (_, None) => break,
+        }
+    }
+
+    strings
+}
+
+// A color line holds `key value` pairs, e.g: "c #FF0000 m black", we prefer the color visual
+fn parse_color_line(line: &str) -> anyhow::Result<[u8; 4]> {
+    const KEYS: [&str; 5] = ["c", "g", "g4", "m", "s"];
+    let mut values: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut current = None;
+    for token in line.split_whitespace() {
+        match current {
+            // Color names can contain spaces, e.g: "light gray"
+            Some(key) if !KEYS.contains(&token) || values[key].is_empty() => {
+                values.get_mut(key).unwrap().push(token)
+            }
+            _ if KEYS.contains(&token) => {
+                current = Some(token);
+                values.insert(token, vec![]);
+            }
+            _ => bail!("invalid xpm color {line:?}"),
+        }
+    }
+
+    let value = ["c", "g", "g4", "m"]
+        .iter()
+        .find_map(|key| values.get(key).filter(|value| !value.is_empty()))
+        .ok_or_else(|| anyhow!("missing xpm color value in {line:?}"))?;
+
+    parse_color(&value.join(" "))
+}
+
+fn parse_color(value: &str) -> anyhow::Result<[u8; 4]> {
+    if value.eq_ignore_ascii_case("none") {
+        return Ok([0, 0, 0, 0]);
+    }
+
+    if let Some(hex) = value.strip_prefix('#') {
+        // Each component has the same number of digits, only the most significant byte is kept
+        let digits = hex.len() / 3;
+        if hex.len() % 3 != 0 || digits == 0 || digits > 4 || !hex.is_ascii() {
+            bail!("invalid xpm color {value:?}");
+        }
+
+        let mut rgba = [0, 0, 0, 255];
+        for (idx, component) in rgba.iter_mut().take(3).enumerate() {
+            let component_hex = &hex[idx * digits..(idx + 1) * digits];
+            let component_value = u16::from_str_radix(component_hex, 16)?;
+            *component = match digits {
+                1 => (component_value * 17) as u8,
+                2 => component_value as u8,
+                _ => (component_value >> ((digits - 2) * 4)) as u8,
+            };
+        }
+
+        return Ok(rgba);
+    }
+
+    named_color(value).ok_or_else(|| anyhow!("unsupported xpm color {value:?}"))
+}
+
+// The X11 color names found in legacy icons
+fn named_color(name: &str) -> Option<[u8; 4]> {
+    let name = name.to_ascii_lowercase().replace(' ', "");
+    let gray_level = name
+        .strip_prefix("gray")
+        .or_else(|| name.strip_prefix("grey"))
+        .filter(|level| !level.is_empty())
+        .and_then(|level| level.parse::<u16>().ok())
+        .filter(|level| *level <= 100);
+
+    if let Some(level) = gray_level {
+        let value = ((level * 255 + 50) / 100) as u8;
+        return Some([value, value, value, 255]);
+    }
+
+    let [r, g, b] = match name.as_str() {
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "green" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" => [0, 255, 255],
+        "magenta" => [255, 0, 255],
+        "gray" | "grey" => [190, 190, 190],
+        "darkgray" | "darkgrey" => [169, 169, 169],
+        "lightgray" | "lightgrey" => [211, 211, 211],
+        "orange" => [255, 165, 0],
+        "brown" => [165, 42, 42],
+        "navy" => [0, 0, 128],
+        _ => return None,
+    };
+
+    Some([r, g, b, 255])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::icons::xpm::{decode, XpmImage};
+    use speculoos::prelude::*;
+
+    const FIXTURE: &str = include_str!("fixtures/checker.xpm");
+
+    #[test]
+    fn should_decode_xpm_fixture() {
+        let image = decode(FIXTURE).unwrap();
+
+        assert_eq!((image.width, image.height), (4, 2));
+        assert_eq!(
+            image.rgba,
+            [
+                [0, 0, 0, 0],
+                [255, 0, 0, 255],
+                [255, 0, 0, 255],
+                [0, 0, 0, 0],
+                [190, 190, 190, 255],
+                [0x12, 0x34, 0x56, 255],
+                [0x12, 0x34, 0x56, 255],
+                [128, 128, 128, 255],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn should_decode_two_chars_per_pixel() {
+        let image = decode(
+            r#"static char *icon[] = {
+            "2 1 2 2",
+            "aa c #FFFFFFFFFFFF",
+            "bb c light gray",
+            "aabb"
+            };"#,
+        );
+
+        asserting!("Should decode wide color keys")
+            .that(&image)
+            .is_ok()
+            .is_equal_to(XpmImage {
+                width: 2,
+                height: 1,
+                rgba: vec![255, 255, 255, 255, 211, 211, 211, 255],
+            });
+    }
+
+    #[test]
+    fn should_reject_truncated_xpm() {
+        let image = decode(
+            r#"static char *icon[] = {
+            "2 2 1 1",
+            ". c #000000",
+            ".."
+            };"#,
+        );
+
+        asserting!("Should fail on missing rows")
+            .that(&image)
+            .is_err();
+    }
+
+    #[test]
+    fn should_reject_bogus_header() {
+        let too_many_colors = decode(r#""1 1 18446744073709551615 1", ". c #000000", ".""#);
+        let too_many_chars = decode(r#""1 1 1 9223372036854775807", ". c #000000", ".""#);
+
+        asserting!("Should not allocate for unusable colors")
+            .that(&too_many_colors)
+            .is_err();
+
+        asserting!("Should not overflow on wide color keys")
+            .that(&too_many_chars)
+            .is_err();
+    }
+}
@@ -1,20 +1,24 @@
 use std::path::PathBuf;
 use std::sync::Mutex;
 
-use anyhow::anyhow;
-use clap::Parser;
+use anyhow::{anyhow, bail};
+use clap::{Parser, Subcommand};
 use once_cell::sync::{Lazy, OnceCell};
-use tracing::{debug, info};
+use tracing::{debug, error, info};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
 use app::style::Theme;
+use db::{DatabaseLocation, DatabaseOptions, DB_OPTIONS};
+use settings::Settings;
 
 pub mod app;
+pub mod calc;
 pub mod config;
 pub mod db;
 pub mod freedesktop;
 pub mod icons;
+pub mod settings;
 
 pub static THEME_PATH: Lazy<Mutex<PathBuf>> = Lazy::new(|| {
     Mutex::new(
@@ -29,6 +33,17 @@ static THEME_SCALE: OnceCell<f32> = OnceCell::new();
 
 pub static THEME: Lazy<Theme> = Lazy::new(Theme::load);
 
+pub static SETTINGS_PATH: Lazy<Mutex<PathBuf>> = Lazy::new(|| {
+    Mutex::new(
+        dirs::config_dir()
+            .ok_or_else(|| anyhow!("Onagre config not found"))
+            .map(|path| path.join("onagre").join("config.toml"))
+            .unwrap(),
+    )
+});
+
+pub static SETTINGS: Lazy<Settings> = Lazy::new(Settings::load);
+
 #[derive(Parser)]
 #[command(name = "onagre", author = "Paul D. <paul.delafosse@protonmail.com>")]
 struct Cli {
@@ -39,11 +54,143 @@ struct Cli {
     )]
     theme: Option<PathBuf>,
 
+    #[arg(
+        long = "config",
+        short = 'c',
+        help = "Path to an alternate onagre config file"
+    )]
+    config: Option<PathBuf>,
+
     #[arg(long = "scale", short = 's', help = "Change the scale of onagre theme")]
     scale: Option<f32>,
 
     #[arg(long = "mode", short = 'm', help = "The mode parameter as a string")]
     mode: Option<String>,
+
+    #[arg(
+        long = "db",
+        env = "ONAGRE_DB",
+        global = true,
+        help = "Path to an alternate history database, ':memory:' to keep it in memory"
+    )]
+    db: Option<DatabaseLocation>,
+
+    #[arg(long = "incognito", help = "Show history but do not record launches")]
+    incognito: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(subcommand, about = "Manage onagre launch history")]
+    History(HistoryCommand),
+    #[command(about = "Print the most used entries of each history collection")]
+    Stats {
+        #[arg(long = "json", help = "Print the statistics as json")]
+        json: bool,
+        #[arg(
+            long = "collection",
+            help = "Only show this collection, e.g: 'desktop-entries', can be repeated"
+        )]
+        collections: Vec<String>,
+        #[arg(
+            long = "since",
+            value_parser = db::stats::parse_age,
+            help = "Only count entries used within this duration, e.g: '7d'"
+        )]
+        since: Option<u64>,
+        #[arg(
+            long = "until",
+            value_parser = db::stats::parse_age,
+            help = "Only count entries last used longer ago than this duration, e.g: '2w'"
+        )]
+        until: Option<u64>,
+        #[arg(
+            long = "limit",
+            default_value_t = 10,
+            help = "Number of entries listed per collection"
+        )]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    #[command(about = "Write the launch history as json to stdout")]
+    Export {
+        #[arg(
+            long = "collection",
+            help = "Only export this collection, e.g: 'desktop-entries', can be repeated"
+        )]
+        collections: Vec<String>,
+    },
+    #[command(about = "Merge a json history export into the database")]
+    Import {
+        #[arg(help = "Path to the export, read from stdin if omitted")]
+        file: Option<PathBuf>,
+    },
+    #[command(about = "Remove history entries exceeding the configured limits")]
+    Prune,
+}
+
+impl Command {
+    fn run(self) -> anyhow::Result<()> {
+        // Changes to a snapshot of a locked database would be discarded
+        let writes = !matches!(
+            self,
+            Command::History(HistoryCommand::Export { .. }) | Command::Stats { .. }
+        );
+        if writes && db::DB.is_incognito() {
+            bail!("The history database is read-only, close other onagre instances and do not use --incognito");
+        }
+
+        match self {
+            Command::History(HistoryCommand::Export { collections }) => {
+                db::history::export(&db::DB, &collections, std::io::stdout().lock())
+            }
+            Command::History(HistoryCommand::Import { file }) => {
+                let summary = match file {
+                    Some(file) => db::history::import(&db::DB, std::fs::File::open(file)?)?,
+                    None => db::history::import(&db::DB, std::io::stdin().lock())?,
+                };
+
+                info!(
+                    "Imported {} new entries, merged {} existing entries",
+                    summary.inserted, summary.merged
+                );
+                Ok(())
+            }
+            Command::History(HistoryCommand::Prune) => {
+                let removed = db::DB.prune()?;
+                info!("Removed {removed} history entries");
+                Ok(())
+            }
+            Command::Stats {
+                json,
+                collections,
+                since,
+                until,
+                limit,
+            } => {
+                let filter = db::stats::StatsFilter {
+                    collections,
+                    since,
+                    until,
+                    limit,
+                };
+
+                let now = db::timestamp();
+                let stats = db::DB.stats(&filter, now)?;
+                if json {
+                    db::stats::write_json(&stats, std::io::stdout().lock())
+                } else {
+                    db::stats::write_table(&stats, now, std::io::stdout().lock())
+                }
+            }
+        }
+    }
 }
 
 pub fn main() -> iced::Result {
@@ -51,7 +198,8 @@ pub fn main() -> iced::Result {
         .with(tracing_subscriber::EnvFilter::new(
             std::env::var("RUST_LOG").unwrap_or_else(|_| "onagre=info".into()),
         ))
-        .with(tracing_subscriber::fmt::layer())
+        // Stdout is kept for commands output
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .init();
 
     info!("Starting onagre");
@@ -67,9 +215,44 @@ pub fn main() -> iced::Result {
         info!("Using alternate theme : {:?}", THEME_PATH.lock().unwrap());
     }
 
+    // User defined config, $XDG_CONFIG_DIR/onagre/config.toml otherwise
+    if let Some(config_path) = cli.config {
+        let path = config_path.canonicalize();
+        if let Ok(path) = path {
+            *SETTINGS_PATH.lock().unwrap() = path;
+        }
+
+        info!(
+            "Using alternate config : {:?}",
+            SETTINGS_PATH.lock().unwrap()
+        );
+    }
+
     if let Some(scale) = cli.scale {
         THEME_SCALE.get_or_init(|| scale);
-        info!("Using scale value : {:?}", scale);
+    }
+
+    // User defined database, $XDG_DATA_HOME/onagre-db otherwise
+    let location = cli.db.unwrap_or_default();
+    let incognito = cli.incognito;
+    info!("Using database : {location:?}");
+    if incognito {
+        info!("Incognito mode, history will not be recorded");
+    }
+
+    DB_OPTIONS.get_or_init(|| DatabaseOptions {
+        location,
+        incognito,
+        history: SETTINGS.history.clone(),
+    });
+
+    if let Some(command) = cli.command {
+        if let Err(err) = command.run() {
+            error!("{err:#}");
+            std::process::exit(1);
+        }
+
+        return Ok(());
     }
 
     if let Some(mode) = cli.mode {
@@ -0,0 +1,191 @@
+use iced::keyboard::{KeyCode, Modifiers};
+use serde::Deserialize;
+use std::convert::TryFrom;
+
+// Configurable key bindings, each written as `modifier+...+key`, e.g: "ctrl+enter"
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct KeyBindings {
+    // Copy the selected row to the clipboard and exit
+    pub copy: KeyBinding,
+    // Mark or unmark the selected row for batch activation
+    pub mark: KeyBinding,
+    // Recall the previous submitted query
+    pub recall: KeyBinding,
+    // Pin or unpin the selected history entry
+    pub pin: KeyBinding,
+    // Add an alias for the selected entry
+    pub alias: KeyBinding,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            copy: KeyBinding::new(Modifiers::CTRL, KeyCode::Enter),
+            mark: KeyBinding::new(Modifiers::CTRL, KeyCode::Space),
+            recall: KeyBinding::new(Modifiers::CTRL, KeyCode::R),
+            pin: KeyBinding::new(Modifiers::CTRL, KeyCode::P),
+            alias: KeyBinding::new(Modifiers::CTRL, KeyCode::K),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct KeyBinding {
+    pub modifiers: Modifiers,
+    pub key_code: KeyCode,
+}
+
+impl KeyBinding {
+    pub const fn new(modifiers: Modifiers, key_code: KeyCode) -> Self {
+        KeyBinding {
+            modifiers,
+            key_code,
+        }
+    }
+
+    pub fn matches(&self, modifiers: Modifiers, key_code: KeyCode) -> bool {
+        self.key_code == key_code && self.modifiers == modifiers
+    }
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let mut modifiers = Modifiers::empty();
+        let mut parts = value.split('+').map(str::trim).peekable();
+        while let Some(part) = parts.next() {
+            let part = part.to_lowercase();
+
+            // The last part is the key itself
+            if parts.peek().is_none() {
+                return key_code(&part)
+                    .map(|key_code| KeyBinding::new(modifiers, key_code))
+                    .ok_or_else(|| format!("unknown key '{part}' in '{value}'"));
+            }
+
+            modifiers |= match part.as_str() {
+                "ctrl" | "control" => Modifiers::CTRL,
+                "shift" => Modifiers::SHIFT,
+                "alt" => Modifiers::ALT,
+                "super" | "logo" | "meta" => Modifiers::LOGO,
+                _ => return Err(format!("unknown modifier '{part}' in '{value}'")),
+            };
+        }
+
+        Err("empty key binding".to_string())
+    }
+}
+
+fn key_code(name: &str) -> Option<KeyCode> {
+    let key_code = match name {
+        "enter" | "return" => KeyCode::Enter,
+        "space" => KeyCode::Space,
+        "tab" => KeyCode::Tab,
+        "escape" | "esc" => KeyCode::Escape,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "f1" => KeyCode::F1,
+        "f2" => KeyCode::F2,
+        "f3" => KeyCode::F3,
+        "f4" => KeyCode::F4,
+        "f5" => KeyCode::F5,
+        "f6" => KeyCode::F6,
+        "f7" => KeyCode::F7,
+        "f8" => KeyCode::F8,
+        "f9" => KeyCode::F9,
+        "f10" => KeyCode::F10,
+        "f11" => KeyCode::F11,
+        "f12" => KeyCode::F12,
+        "a" => KeyCode::A,
+        "b" => KeyCode::B,
+        "c" => KeyCode::C,
+        "d" => KeyCode::D,
+        "e" => KeyCode::E,
+        "f" => KeyCode::F,
+        "g" => KeyCode::G,
+        "h" => KeyCode::H,
+        "i" => KeyCode::I,
+        "j" => KeyCode::J,
+        "k" => KeyCode::K,
+        "l" => KeyCode::L,
+        "m" => KeyCode::M,
+        "n" => KeyCode::N,
+        "o" => KeyCode::O,
+        "p" => KeyCode::P,
+        "q" => KeyCode::Q,
+        "r" => KeyCode::R,
+        "s" => KeyCode::S,
+        "t" => KeyCode::T,
+        "u" => KeyCode::U,
+        "v" => KeyCode::V,
+        "w" => KeyCode::W,
+        "x" => KeyCode::X,
+        "y" => KeyCode::Y,
+        "z" => KeyCode::Z,
+        "0" => KeyCode::Key0,
+        "1" => KeyCode::Key1,
+        "2" => KeyCode::Key2,
+        "3" => KeyCode::Key3,
+        "4" => KeyCode::Key4,
+        "5" => KeyCode::Key5,
+        "6" => KeyCode::Key6,
+        "7" => KeyCode::Key7,
+        "8" => KeyCode::Key8,
+        "9" => KeyCode::Key9,
+        _ => return None,
+    };
+
+    Some(key_code)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::settings::keybindings::KeyBinding;
+    use iced::keyboard::{KeyCode, Modifiers};
+    use speculoos::prelude::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn should_parse_key_binding_with_modifiers() {
+        let binding = KeyBinding::try_from("Ctrl+Shift+c".to_string());
+
+        asserting!("Should parse modifiers and key")
+            .that(&binding)
+            .is_ok()
+            .is_equal_to(KeyBinding::new(
+                Modifiers::CTRL | Modifiers::SHIFT,
+                KeyCode::C,
+            ));
+    }
+
+    #[test]
+    fn should_parse_key_binding_without_modifier() {
+        let binding = KeyBinding::try_from("F5".to_string());
+
+        asserting!("Should parse a single key")
+            .that(&binding)
+            .is_ok()
+            .is_equal_to(KeyBinding::new(Modifiers::empty(), KeyCode::F5));
+    }
+
+    #[test]
+    fn should_reject_unknown_modifier() {
+        let binding = KeyBinding::try_from("hyper+enter".to_string());
+
+        asserting!("Should fail on unknown modifier")
+            .that(&binding)
+            .is_err();
+    }
+}
@@ -0,0 +1,395 @@
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{error, warn};
+
+use crate::settings::keybindings::KeyBindings;
+use crate::SETTINGS_PATH;
+
+pub mod keybindings;
+
+// Non styling options, read from `$XDG_CONFIG_DIR/onagre/config.toml`
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Settings {
+    pub backend: Backend,
+    pub script_plugins: Vec<ScriptPluginConfig>,
+    pub calculator: CalculatorConfig,
+    pub keybindings: KeyBindings,
+    // Command receiving the copied text on stdin, defaults to `wl-copy` or `xclip`
+    pub clipboard_command: Option<String>,
+    // Desktop file ids always shown first in the history, e.g: "firefox.desktop"
+    pub pinned: Vec<String>,
+    // Move pinned entries first in desktop entry search results
+    pub boost_pinned: bool,
+    // Keywords launching their target directly, e.g: `ff = { desktop-entry = "firefox.desktop" }`
+    pub aliases: HashMap<String, AliasTarget>,
+    pub web_shortcuts: Vec<WebShortcutConfig>,
+    pub history: HistoryConfig,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    // Spawn `pop-launcher` and talk to it over stdio
+    #[default]
+    Process,
+    // Run the launcher service on a thread of the onagre process
+    InProcess,
+}
+
+// A lightweight plugin running a command for each query, see `app::scripts`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScriptPluginConfig {
+    pub name: String,
+    // Typing this prefix enables the plugin
+    pub prefix: String,
+    pub icon: Option<String>,
+    // Command returning results, `{query}` is replaced with the user query
+    pub command: String,
+    #[serde(default)]
+    pub input: ScriptInput,
+    // Command run on activation, `{value}` is replaced with the selected result value
+    pub activate: String,
+    #[serde(default)]
+    pub history: bool,
+}
+
+// The native calculator mode, see `calc`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CalculatorConfig {
+    // Typing this prefix enables the calculator
+    pub prefix: String,
+}
+
+impl Default for CalculatorConfig {
+    fn default() -> Self {
+        CalculatorConfig {
+            prefix: "=".to_string(),
+        }
+    }
+}
+
+// A web search opened by onagre, without the pop-launcher web plugin
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct WebShortcutConfig {
+    // Typing this prefix followed by a space enables the shortcut, e.g: "gh"
+    pub prefix: String,
+    // Search url, `{query}` is replaced with the encoded query
+    pub url: String,
+    pub icon: Option<String>,
+}
+
+impl WebShortcutConfig {
+    // The query is appended when the url has no `{query}` placeholder
+    pub fn url(&self, query: &str) -> String {
+        let query = utf8_percent_encode(query.trim(), NON_ALPHANUMERIC).to_string();
+        if self.url.contains("{query}") {
+            self.url.replace("{query}", &query)
+        } else {
+            format!("{}{query}", self.url)
+        }
+    }
+}
+
+// Size limits of the history collections, unlimited by default
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct HistoryConfig {
+    pub max_entries: Option<usize>,
+    pub max_age_days: Option<u64>,
+    // Per collection overrides, e.g: `[history.collections.ddg]`
+    pub collections: HashMap<String, HistoryLimits>,
+    // Rank desktop entries by time of day and weekday instead of launch count only
+    pub contextual_ranking: bool,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            max_entries: None,
+            max_age_days: None,
+            collections: HashMap::new(),
+            contextual_ranking: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct HistoryLimits {
+    pub max_entries: Option<usize>,
+    pub max_age_days: Option<u64>,
+}
+
+impl HistoryConfig {
+    // The collection overrides, falling back to the global limits
+    pub fn limits(&self, collection: &str) -> HistoryLimits {
+        let limits = self
+            .collections
+            .get(collection)
+            .copied()
+            .unwrap_or_default();
+        HistoryLimits {
+            max_entries: limits.max_entries.or(self.max_entries),
+            max_age_days: limits.max_age_days.or(self.max_age_days),
+        }
+    }
+}
+
+// What an alias keyword activates
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AliasTarget {
+    // A desktop file id, e.g: "firefox.desktop"
+    DesktopEntry(String),
+    // A query run as if it was typed, e.g: "ddg rust" or "= 2 + 2"
+    Query(String),
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptInput {
+    // Pass the query as the last argument, unless the command has a `{query}` placeholder
+    #[default]
+    Argument,
+    // Write the query on the command stdin
+    Stdin,
+}
+
+impl Settings {
+    pub fn script_plugin(&self, name: &str) -> Option<&ScriptPluginConfig> {
+        self.script_plugins
+            .iter()
+            .find(|plugin| plugin.name == name)
+    }
+
+    pub fn web_shortcut(&self, prefix: &str) -> Option<&WebShortcutConfig> {
+        self.web_shortcuts
+            .iter()
+            .find(|shortcut| shortcut.prefix == prefix)
+    }
+
+    pub fn load() -> Self {
+        let path = SETTINGS_PATH.lock().unwrap().clone();
+        if !path.exists() {
+            return Settings::default();
+        }
+
+        match Self::from_path(&path) {
+            Ok(settings) => settings,
+            Err(err) => {
+                error!("Failed to parse config {path:?}: {err}");
+                warn!("Failing back to default config");
+                Settings::default()
+            }
+        }
+    }
+
+    fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::settings::keybindings::KeyBinding;
+    use crate::settings::{
+        AliasTarget, Backend, CalculatorConfig, HistoryLimits, ScriptInput, ScriptPluginConfig,
+        Settings, WebShortcutConfig,
+    };
+    use iced::keyboard::{KeyCode, Modifiers};
+    use speculoos::prelude::*;
+
+    #[test]
+    fn should_parse_backend() {
+        let settings: Result<Settings, _> = toml::from_str(r#"backend = "in-process""#);
+
+        asserting!("Should parse 'backend' option")
+            .that(&settings.map(|settings| settings.backend))
+            .is_ok()
+            .is_equal_to(Backend::InProcess);
+    }
+
+    #[test]
+    fn should_default_to_process_backend() {
+        let settings: Result<Settings, _> = toml::from_str("");
+
+        asserting!("Should default to the pop-launcher process")
+            .that(&settings)
+            .is_ok()
+            .is_equal_to(Settings::default());
+    }
+
+    #[test]
+    fn should_parse_script_plugins() {
+        let settings: Result<Settings, _> = toml::from_str(
+            r#"
+            [[script-plugins]]
+            name = "pass"
+            prefix = "pass "
+            command = "pass-search {query}"
+            input = "stdin"
+            activate = "pass -c {value}"
+            history = true
+        "#,
+        );
+
+        asserting!("Should parse 'script-plugins' entries")
+            .that(&settings.map(|settings| settings.script_plugins))
+            .is_ok()
+            .is_equal_to(vec![ScriptPluginConfig {
+                name: "pass".to_string(),
+                prefix: "pass ".to_string(),
+                icon: None,
+                command: "pass-search {query}".to_string(),
+                input: ScriptInput::Stdin,
+                activate: "pass -c {value}".to_string(),
+                history: true,
+            }]);
+    }
+
+    #[test]
+    fn should_parse_calculator_prefix() {
+        let settings: Result<Settings, _> = toml::from_str(
+            r#"
+            [calculator]
+            prefix = "calc "
+        "#,
+        );
+
+        asserting!("Should parse 'calculator' table")
+            .that(&settings.map(|settings| settings.calculator))
+            .is_ok()
+            .is_equal_to(CalculatorConfig {
+                prefix: "calc ".to_string(),
+            });
+    }
+
+    #[test]
+    fn should_parse_keybindings() {
+        let settings: Result<Settings, _> = toml::from_str(
+            r#"
+            clipboard-command = "xsel --clipboard --input"
+
+            [keybindings]
+            copy = "alt+c"
+        "#,
+        );
+
+        let settings = settings.unwrap();
+        assert_eq!(
+            settings.keybindings.copy,
+            KeyBinding::new(Modifiers::ALT, KeyCode::C)
+        );
+        assert_eq!(
+            settings.clipboard_command.as_deref(),
+            Some("xsel --clipboard --input")
+        );
+    }
+
+    #[test]
+    fn should_parse_aliases() {
+        let settings: Result<Settings, _> = toml::from_str(
+            r#"
+            [aliases]
+            ff = { desktop-entry = "firefox.desktop" }
+            rs = { query = "ddg rust" }
+        "#,
+        );
+
+        let aliases = settings.unwrap().aliases;
+        assert_eq!(
+            aliases.get("ff"),
+            Some(&AliasTarget::DesktopEntry("firefox.desktop".to_string()))
+        );
+        assert_eq!(
+            aliases.get("rs"),
+            Some(&AliasTarget::Query("ddg rust".to_string()))
+        );
+    }
+
+    #[test]
+    fn should_parse_web_shortcuts() {
+        let settings: Result<Settings, _> = toml::from_str(
+            r#"
+            [[web-shortcuts]]
+            prefix = "gh"
+            url = "https://github.com/search?q={query}&type=repositories"
+            icon = "github"
+        "#,
+        );
+
+        asserting!("Should parse 'web-shortcuts' entries")
+            .that(&settings.map(|settings| settings.web_shortcuts))
+            .is_ok()
+            .is_equal_to(vec![WebShortcutConfig {
+                prefix: "gh".to_string(),
+                url: "https://github.com/search?q={query}&type=repositories".to_string(),
+                icon: Some("github".to_string()),
+            }]);
+    }
+
+    #[test]
+    fn should_encode_web_shortcut_query() {
+        let shortcut = WebShortcutConfig {
+            prefix: "gh".to_string(),
+            url: "https://github.com/search?q={query}&type=repositories".to_string(),
+            icon: None,
+        };
+
+        assert_eq!(
+            shortcut.url(" onagre launcher&co "),
+            "https://github.com/search?q=onagre%20launcher%26co&type=repositories"
+        );
+    }
+
+    #[test]
+    fn should_append_web_shortcut_query_without_placeholder() {
+        let shortcut = WebShortcutConfig {
+            prefix: "crates".to_string(),
+            url: "https://crates.io/search?q=".to_string(),
+            icon: None,
+        };
+
+        assert_eq!(shortcut.url("redb"), "https://crates.io/search?q=redb");
+    }
+
+    #[test]
+    fn should_override_history_limits_per_collection() {
+        let settings: Result<Settings, _> = toml::from_str(
+            r#"
+            [history]
+            max-entries = 500
+            max-age-days = 365
+
+            [history.collections.ddg]
+            max-entries = 50
+        "#,
+        );
+
+        let history = settings.unwrap().history;
+        asserting!("Should rank by time of day by default")
+            .that(&history.contextual_ranking)
+            .is_true();
+
+        asserting!("Should override max entries only")
+            .that(&history.limits("ddg"))
+            .is_equal_to(HistoryLimits {
+                max_entries: Some(50),
+                max_age_days: Some(365),
+            });
+
+        asserting!("Should use the global limits")
+            .that(&history.limits("desktop-entries"))
+            .is_equal_to(HistoryLimits {
+                max_entries: Some(500),
+                max_age_days: Some(365),
+            });
+    }
+}